@@ -0,0 +1,120 @@
+#!/usr/bin/env perl
+# Generates src/unicode_tables.rs from the Unicode database shipped with Perl:
+#
+#     perl scripts/generate-unicode-tables.pl > src/unicode_tables.rs
+#     rustfmt src/unicode_tables.rs
+use strict;
+use warnings;
+use Unicode::UCD qw(prop_aliases prop_invlist prop_invmap prop_value_aliases prop_values);
+
+# Inclusive ranges of the code points that have the property, without the surrogates
+# as they are not valid `char`s.
+sub ranges {
+    my ($property) = @_;
+    my @inversion = prop_invlist($property);
+    push @inversion, 0x110000 if @inversion % 2;
+
+    my @ranges;
+    for (my $i = 0; $i < @inversion; $i += 2) {
+        my ($start, $end) = ($inversion[$i], $inversion[$i + 1] - 1);
+        push @ranges, [$start, 0xD7FF] if $start < 0xD800 && $end >= 0xD800;
+        $start = 0xE000 if $start < 0xE000 && $end >= 0xD800;
+        push @ranges, [$start, $end] if $start <= $end;
+    }
+    return @ranges;
+}
+
+sub ranges_literal {
+    my ($property) = @_;
+    return join "", map { sprintf("('\\u{%X}', '\\u{%X}'),", @$_) } ranges($property);
+}
+
+sub table {
+    my ($name, $property) = @_;
+    return "pub const $name: Ranges = &[" . ranges_literal($property) . "];\n";
+}
+
+# A table of named classes, each of them listed with all its names.
+sub named_tables {
+    my ($name, @classes) = @_;
+    my $table = "pub const $name: NamedRanges = &[\n";
+    for my $class (@classes) {
+        my ($property, @names) = @$class;
+        my %seen;
+        my $names = join ", ", map { "\"$_\"" } grep { !$seen{$_}++ } @names;
+        $table .= "(&[$names], &[" . ranges_literal($property) . "]),\n";
+    }
+    return $table . "];\n";
+}
+
+# Every character that has other cases, with all the characters that have the same
+# simple case folding, like 'k' with 'K' and the Kelvin sign.
+sub case_folding {
+    my ($list, $map, $format, $default) = prop_invmap("Simple_Case_Folding");
+    my %folds;
+    for my $i (0 .. $#$list - 1) {
+        next if $map->[$i] eq $default;
+        for my $c ($list->[$i] .. $list->[$i + 1] - 1) {
+            my $folded = $format eq "a" ? $map->[$i] + $c - $list->[$i] : $map->[$i];
+            push @{ $folds{$folded} }, $c if $folded != $c;
+        }
+    }
+
+    my %variants;
+    for my $folded (keys %folds) {
+        my @orbit = sort { $a <=> $b } $folded, @{ $folds{$folded} };
+        for my $c (@orbit) {
+            $variants{$c} = [grep { $_ != $c } @orbit];
+        }
+    }
+
+    my $table = "pub const CASE_FOLDING: &[(char, &[char])] = &[\n";
+    for my $c (sort { $a <=> $b } keys %variants) {
+        my $others = join ", ", map { sprintf("'\\u{%X}'", $_) } @{ $variants{$c} };
+        $table .= sprintf("('\\u{%X}', &[%s]),\n", $c, $others);
+    }
+    return $table . "];\n";
+}
+
+my @binary_properties = qw(
+    ASCII_Hex_Digit Alphabetic Bidi_Control Bidi_Mirrored Case_Ignorable Cased
+    Changes_When_Casefolded Changes_When_Casemapped Changes_When_Lowercased
+    Changes_When_Titlecased Changes_When_Uppercased Dash Default_Ignorable_Code_Point
+    Deprecated Diacritic Emoji Emoji_Component Emoji_Modifier Emoji_Modifier_Base
+    Emoji_Presentation Extended_Pictographic Extender Grapheme_Base Grapheme_Extend
+    Hex_Digit IDS_Binary_Operator IDS_Trinary_Operator ID_Continue ID_Start Ideographic
+    Join_Control Logical_Order_Exception Lowercase Math Noncharacter_Code_Point
+    Pattern_Syntax Pattern_White_Space Quotation_Mark Radical Regional_Indicator
+    Sentence_Terminal Soft_Dotted Terminal_Punctuation Unified_Ideograph Uppercase
+    Variation_Selector White_Space XID_Continue XID_Start
+    Any ASCII Assigned
+);
+
+printf "// Generated by scripts/generate-unicode-tables.pl from Unicode %s, do not edit.\n\n",
+    Unicode::UCD::UnicodeVersion();
+
+print "/// Sorted inclusive ranges of characters.\n";
+print "pub type Ranges = &'static [(char, char)];\n\n";
+print "/// Classes listed with all their names.\n";
+print "pub type NamedRanges = &'static [(&'static [&'static str], Ranges)];\n\n";
+
+print "/// Decimal digits, the `Nd` general category.\n";
+print table("DIGIT", "Nd"), "\n";
+print "/// The `White_Space` property.\n";
+print table("SPACE", "White_Space"), "\n";
+print "/// Word characters as defined by UTS #18: alphabetic characters, marks,\n";
+print "/// decimal digits, connector punctuation and join controls.\n";
+print table("WORD", "Word"), "\n";
+
+print "/// General categories by their short and long names, the groups like `L` included.\n";
+print named_tables(
+    "GENERAL_CATEGORY",
+    map { ["gc=$_", prop_value_aliases("gc", $_)] } prop_values("gc")
+), "\n";
+print "/// Scripts by their short and long names.\n";
+print named_tables("SCRIPT", map { ["sc=$_", prop_value_aliases("sc", $_)] } prop_values("sc")),
+    "\n";
+print "/// Binary properties by their short and long names.\n";
+print named_tables("BINARY_PROPERTY", map { [$_, prop_aliases($_)] } @binary_properties), "\n";
+print "/// Characters sorted by code point, with the other characters of the same simple case folding.\n";
+print case_folding();
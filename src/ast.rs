@@ -0,0 +1,78 @@
+use std::ops::Range;
+
+use crate::class::CharClass;
+
+/// The syntax tree of a pattern. Every node keeps the byte span of the pattern it was parsed
+/// from, and the flags that were in effect there are already applied to it,
+/// so that the tree can be compiled or analysed without going back to the pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ast {
+    pub kind: AstKind,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AstKind {
+    /// Matches the empty text, like an empty pattern or an empty alternative.
+    Empty,
+    Literal {
+        c: char,
+        case_insensitive: bool,
+    },
+    /// Matches a character of the class, or any character outside of it when `negated`.
+    Class {
+        class: CharClass,
+        negated: bool,
+        case_insensitive: bool,
+    },
+    /// `.`, which matches any character but `\n`, and `\r` too in CRLF mode,
+    /// unless it matches new lines.
+    Dot {
+        matches_new_line: bool,
+        crlf: bool,
+    },
+    Assertion(Assertion),
+    /// A group, which is capturing when it has an index.
+    Group {
+        index: Option<usize>,
+        inner: Box<Ast>,
+    },
+    /// Matches `inner` from `min` to `max` times, or at least `min` times without a `max`.
+    /// A greedy repetition prefers matching as many times as possible, a lazy one as few.
+    Repetition {
+        inner: Box<Ast>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+    Concat(Vec<Ast>),
+    /// Matches any of the alternatives, preferring the first ones.
+    Alternation(Vec<Ast>),
+}
+
+/// Matches a position of the text rather than a character, named like the NFA arrows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assertion {
+    /// Start of the text, `^` or `\A`.
+    LineStart,
+    /// End of the text, `$` or `\z`.
+    LineEnd,
+    /// `^` in multi-line mode.
+    MultiLineStart,
+    /// `$` in multi-line mode.
+    MultiLineEnd,
+    /// `^` in multi-line and CRLF mode.
+    CrlfLineStart,
+    /// `$` in multi-line and CRLF mode.
+    CrlfLineEnd,
+    /// `\b`.
+    WordBoundary { unicode: bool },
+    /// `\B`.
+    NotWordBoundary { unicode: bool },
+}
+
+impl Ast {
+    pub fn new(kind: AstKind, span: Range<usize>) -> Self {
+        Self { kind, span }
+    }
+}
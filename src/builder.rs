@@ -0,0 +1,292 @@
+use crate::dfa::{DEFAULT_CACHE_SIZE, DFA};
+use crate::error::RegexError;
+use crate::parser::{self, Flags};
+use crate::regex::Regex;
+
+/// The number of NFA nodes a pattern can compile to by default.
+pub const DEFAULT_NFA_SIZE_LIMIT: usize = 1_000_000;
+
+/// Compiles a `Regex` with options that are not part of the pattern itself.
+///
+/// ```
+/// use re::RegexBuilder;
+///
+/// let regex = RegexBuilder::new("^abc$")
+///     .case_insensitive(true)
+///     .multi_line(true)
+///     .build()
+///     .unwrap();
+/// assert!(regex.is_match("xyz\nABC"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegexBuilder {
+    pattern: String,
+    flags: Flags,
+    nfa_size_limit: usize,
+    dfa_cache_size: usize,
+    anchored: bool,
+}
+
+impl RegexBuilder {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            flags: Flags::default(),
+            nfa_size_limit: DEFAULT_NFA_SIZE_LIMIT,
+            dfa_cache_size: DEFAULT_CACHE_SIZE,
+            anchored: false,
+        }
+    }
+
+    /// Letters match both their upper and lower case.
+    pub fn case_insensitive(mut self, yes: bool) -> Self {
+        self.flags.case_insensitive = yes;
+        self
+    }
+
+    /// `^` and `$` match at the start and the end of every line
+    /// instead of only at the start and the end of the text.
+    pub fn multi_line(mut self, yes: bool) -> Self {
+        self.flags.multi_line = yes;
+        self
+    }
+
+    /// `.` matches any character, `\n` included.
+    pub fn dot_matches_new_line(mut self, yes: bool) -> Self {
+        self.flags.dot_matches_new_line = yes;
+        self
+    }
+
+    /// Lines may also end with `\r\n`: in multi-line mode `^` and `$` match around `\r`
+    /// without matching between `\r` and `\n`, and `.` never matches `\r`.
+    pub fn crlf(mut self, yes: bool) -> Self {
+        self.flags.crlf = yes;
+        self
+    }
+
+    /// Whitespace in the pattern is ignored and `#` starts a comment up to the end of the line.
+    /// Escaped whitespace and whitespace in character classes are still matched.
+    pub fn verbose(mut self, yes: bool) -> Self {
+        self.flags.verbose = yes;
+        self
+    }
+
+    /// `\d`, `\w` and `\s` match any Unicode digit, word character or white space.
+    /// When disabled they only match ASCII characters. Enabled by default.
+    pub fn unicode(mut self, yes: bool) -> Self {
+        self.flags.unicode = yes;
+        self
+    }
+
+    /// The largest count accepted in a counted repetition like `a{3}` or `a{2,5}`.
+    pub fn repetition_limit(mut self, limit: usize) -> Self {
+        self.flags.repetition_limit = limit;
+        self
+    }
+
+    /// The deepest nesting of groups and character classes accepted in the pattern,
+    /// like 2 for `(a[bc])`. Deeply nested patterns from untrusted sources fail
+    /// with `NestingTooDeep` instead of overflowing the stack.
+    pub fn nesting_limit(mut self, limit: usize) -> Self {
+        self.flags.nesting_limit = limit;
+        self
+    }
+
+    /// The maximum number of NFA nodes the pattern can compile to.
+    pub fn nfa_size_limit(mut self, limit: usize) -> Self {
+        self.nfa_size_limit = limit;
+        self
+    }
+
+    /// The number of DFA states to cache before the cache is cleared.
+    pub fn dfa_cache_size(mut self, states: usize) -> Self {
+        self.dfa_cache_size = states;
+        self
+    }
+
+    /// Only report matches that start where the search starts: at the beginning of the text,
+    /// or for `find_iter` and friends where the previous match ended.
+    pub fn anchored(mut self, yes: bool) -> Self {
+        self.anchored = yes;
+        self
+    }
+
+    pub fn build(&self) -> Result<Regex, RegexError> {
+        let parsed = parser::parse_with_limit(&self.pattern, self.flags, self.nfa_size_limit)?;
+
+        let dfa = DFA::new(parsed.nfa.clone())
+            .anchored(self.anchored)
+            .cache_size(self.dfa_cache_size);
+        Ok(Regex::from_parsed(
+            &self.pattern,
+            parsed,
+            dfa,
+            self.anchored,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod builder_test {
+    use super::*;
+    use crate::error::ErrorKind::SizeLimitExceeded;
+
+    #[test]
+    fn test_case_insensitive() {
+        let regex = RegexBuilder::new("a[bc]d[^e]")
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        assert!(regex.is_match("AbDx"));
+        assert!(regex.is_match("aCdf"));
+        assert!(!regex.is_match("abdE"));
+        assert_eq!(regex.find("xxABDZ").unwrap().range(), 2..6);
+        assert!(!RegexBuilder::new("ab").build().unwrap().is_match("AB"));
+    }
+
+    #[test]
+    fn test_multi_line() {
+        let regex = RegexBuilder::new("^[ab]+$")
+            .multi_line(true)
+            .build()
+            .unwrap();
+        assert!(regex.is_match("xyz\nabba\nxyz"));
+        assert!(!regex.is_match("xyz\nabbax\nxyz"));
+        let lines: Vec<_> = regex.find_iter("ab\nc\nba").map(|m| m.as_str()).collect();
+        assert_eq!(lines, vec!["ab", "ba"]);
+
+        let regex = RegexBuilder::new("^[ab]+$").build().unwrap();
+        assert!(!regex.is_match("xyz\nabba\nxyz"));
+    }
+
+    #[test]
+    fn test_dot_matches_new_line() {
+        let regex = RegexBuilder::new("a.b").build().unwrap();
+        assert!(!regex.is_match("a\nb"));
+        assert!(regex.is_match("a-b"));
+
+        let regex = RegexBuilder::new("a.b")
+            .dot_matches_new_line(true)
+            .build()
+            .unwrap();
+        assert!(regex.is_match("a\nb"));
+        assert_eq!(regex.find("xa\nb").unwrap().range(), 1..4);
+    }
+
+    #[test]
+    fn test_nfa_size_limit() {
+        let error = RegexBuilder::new("abc")
+            .nfa_size_limit(3)
+            .build()
+            .unwrap_err();
+        assert_eq!(error.kind(), SizeLimitExceeded);
+        assert!(RegexBuilder::new("abc").nfa_size_limit(4).build().is_ok());
+    }
+
+    #[test]
+    fn test_unicode() {
+        let regex = RegexBuilder::new(r"^\w+\s\d$").build().unwrap();
+        assert!(regex.is_match("été\u{A0}٣"));
+        assert!(regex.is_match("abc 3"));
+        let regex = RegexBuilder::new(r"^\w+\s\d$")
+            .unicode(false)
+            .build()
+            .unwrap();
+        assert!(!regex.is_match("été\u{A0}٣"));
+        assert!(regex.is_match("abc 3"));
+        let regex = RegexBuilder::new(r"^[\W]$").unicode(false).build().unwrap();
+        assert!(regex.is_match("é"));
+        let regex = RegexBuilder::new(r"\bé").unicode(false).build().unwrap();
+        assert!(!regex.is_match("é") && regex.is_match("aé"));
+    }
+
+    #[test]
+    fn test_verbose() {
+        let regex = RegexBuilder::new(
+            r"^
+            (?P<year>\d{4}) - # the year
+            (?P<month>\d{2})  # the month
+            [ ]\ $",
+        )
+        .verbose(true)
+        .build()
+        .unwrap();
+        assert!(regex.is_match("2024-05  "));
+        assert!(!regex.is_match("2024 - 05  "));
+        assert_eq!(&regex.captures("2024-05  ").unwrap()["month"], "05");
+        assert!(RegexBuilder::new("a b").build().unwrap().is_match("a b"));
+    }
+
+    #[test]
+    fn test_crlf() {
+        let regex = RegexBuilder::new("^b$").multi_line(true).build().unwrap();
+        assert!(!regex.is_match("a\r\nb\r\n"));
+        let regex = RegexBuilder::new("^b$")
+            .multi_line(true)
+            .crlf(true)
+            .build()
+            .unwrap();
+        assert!(regex.is_match("a\r\nb\r\n"));
+        assert_eq!(regex.find("a\r\nb\r\n").unwrap().range(), 3..4);
+    }
+
+    #[test]
+    fn test_repetition_limit() {
+        assert!(RegexBuilder::new("a{1001}").build().is_err());
+        let regex = RegexBuilder::new("a{1001}")
+            .repetition_limit(1001)
+            .build()
+            .unwrap();
+        assert!(regex.is_match(&"a".repeat(1001)));
+        assert!(!regex.is_match(&"a".repeat(1000)));
+        let error = RegexBuilder::new("a{3}")
+            .repetition_limit(2)
+            .build()
+            .unwrap_err();
+        assert_eq!(error.kind(), crate::error::ErrorKind::RepetitionTooLarge);
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let deep = format!("{}a{}", "(".repeat(100_000), ")".repeat(100_000));
+        let error = RegexBuilder::new(&deep).build().unwrap_err();
+        assert_eq!(error.kind(), crate::error::ErrorKind::NestingTooDeep);
+        assert_eq!(error.span(), 250..251);
+        assert!(RegexBuilder::new(&"[".repeat(100_000)).build().is_err());
+        let deepest = format!("{}a{}", "(".repeat(250), ")".repeat(250));
+        assert!(RegexBuilder::new(&deepest).build().unwrap().is_match("a"));
+        assert!(RegexBuilder::new("(a[bc])")
+            .nesting_limit(2)
+            .build()
+            .is_ok());
+        assert!(RegexBuilder::new("(a[[bc]])")
+            .nesting_limit(2)
+            .build()
+            .is_err());
+        let regex = RegexBuilder::new("((((a))))")
+            .nesting_limit(4)
+            .build()
+            .unwrap();
+        assert_eq!(&regex.captures("a").unwrap()[4], "a");
+    }
+
+    #[test]
+    fn test_dfa_cache_size() {
+        let regex = RegexBuilder::new("(a|b)*abb")
+            .dfa_cache_size(3)
+            .build()
+            .unwrap();
+        assert!(regex.is_match("babababaabb"));
+        assert!(!regex.is_match("babababaab"));
+    }
+
+    #[test]
+    fn test_anchored() {
+        let regex = RegexBuilder::new("[ab]").anchored(true).build().unwrap();
+        assert!(regex.is_match("axx"));
+        assert!(!regex.is_match("xxa"));
+        assert_eq!(regex.find("xa"), None);
+        let found: Vec<_> = regex.find_iter("abxa").map(|m| m.as_str()).collect();
+        assert_eq!(found, vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,338 @@
+use std::fmt;
+
+use crate::unicode_tables::{self, NamedRanges, BINARY_PROPERTY, GENERAL_CATEGORY, SCRIPT};
+
+/// A set of characters stored as sorted ranges that neither overlap nor touch,
+/// so that `[a-z]` takes one range rather than 26 characters.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct CharClass {
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    /// Builds the class from inclusive ranges in any order, which may overlap.
+    pub fn new<I: IntoIterator<Item = (char, char)>>(ranges: I) -> Self {
+        let mut ranges: Vec<_> = ranges.into_iter().filter(|(a, b)| a <= b).collect();
+        ranges.sort_unstable();
+
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some((_, last)) if next_char(*last).is_none_or(|next| start <= next) => {
+                    *last = end.max(*last)
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+        Self { ranges: merged }
+    }
+
+    /// The class of a Perl shorthand: `d` for digits, `w` for word characters
+    /// and `s` for white space. Without `unicode` they only match ASCII characters.
+    pub fn perl(name: char, unicode: bool) -> Option<Self> {
+        let ranges: &[(char, char)] = match (name, unicode) {
+            ('d', true) => unicode_tables::DIGIT,
+            ('w', true) => unicode_tables::WORD,
+            ('s', true) => unicode_tables::SPACE,
+            ('d', false) => &[('0', '9')],
+            ('w', false) => &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')],
+            ('s', false) => &[('\t', '\r'), (' ', ' ')],
+            _ => return None,
+        };
+        Some(Self::new(ranges.iter().cloned()))
+    }
+
+    /// The class of a POSIX bracket expression like `[:alpha:]`, by its name.
+    /// They only match ASCII characters.
+    pub fn posix(name: &str) -> Option<Self> {
+        let ranges: &[(char, char)] = match name {
+            "alnum" => &[('0', '9'), ('A', 'Z'), ('a', 'z')],
+            "alpha" => &[('A', 'Z'), ('a', 'z')],
+            "blank" => &[('\t', '\t'), (' ', ' ')],
+            "cntrl" => &[('\0', '\u{1F}'), ('\u{7F}', '\u{7F}')],
+            "digit" => &[('0', '9')],
+            "graph" => &[('!', '~')],
+            "lower" => &[('a', 'z')],
+            "print" => &[(' ', '~')],
+            "punct" => &[('!', '/'), (':', '@'), ('[', '`'), ('{', '~')],
+            "space" => &[('\t', '\r'), (' ', ' ')],
+            "upper" => &[('A', 'Z')],
+            "xdigit" => &[('0', '9'), ('A', 'F'), ('a', 'f')],
+            _ => return None,
+        };
+        Some(Self::new(ranges.iter().cloned()))
+    }
+
+    /// The class of a Unicode general category, script or binary property, by any of its names.
+    /// Names are compared ignoring case, spaces, `_` and `-`. A `gc=` or `sc=` prefix,
+    /// or their long forms `General_Category=` and `Script=`, only look for a category or a script.
+    pub fn property(name: &str) -> Option<Self> {
+        let (tables, value): (&[NamedRanges], &str) = match name.split_once('=') {
+            None => (&[GENERAL_CATEGORY, SCRIPT, BINARY_PROPERTY], name),
+            Some((property, value)) => match loose(property).as_str() {
+                "gc" | "generalcategory" => (&[GENERAL_CATEGORY], value),
+                "sc" | "script" => (&[SCRIPT], value),
+                _ => return None,
+            },
+        };
+
+        let value = loose(value);
+        tables
+            .iter()
+            .flat_map(|table| table.iter())
+            .find(|(names, _)| names.iter().any(|name| loose(name) == value))
+            .map(|(_, ranges)| Self::new(ranges.iter().cloned()))
+    }
+
+    pub fn from_chars<I: IntoIterator<Item = char>>(chars: I) -> Self {
+        Self::new(chars.into_iter().map(|c| (c, c)))
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        ranges_contain(&self.ranges, c)
+    }
+
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::new(self.ranges.iter().chain(&other.ranges).cloned())
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.negate().union(&other.negate()).negate()
+    }
+
+    /// Returns the characters of this class that are not in the other one.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.intersection(&other.negate())
+    }
+
+    /// Returns the characters that are in exactly one of the two classes.
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.difference(other).union(&other.difference(self))
+    }
+
+    /// Returns the class of all the characters that are not in this one.
+    pub fn negate(&self) -> Self {
+        let mut ranges = vec![];
+        let mut from = Some('\0');
+
+        for &(start, end) in &self.ranges {
+            if let Some(from) = from.filter(|&from| from < start) {
+                ranges.push((from, previous_char(start)));
+            }
+            from = next_char(end);
+        }
+        if let Some(from) = from {
+            ranges.push((from, char::MAX));
+        }
+
+        Self { ranges }
+    }
+}
+
+/// Returns true if `c` is a word character as matched by `\w` in Unicode mode.
+pub fn is_word_char(c: char) -> bool {
+    ranges_contain(unicode_tables::WORD, c)
+}
+
+fn ranges_contain(ranges: &[(char, char)], c: char) -> bool {
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if start > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// A property name in the form used to compare names.
+fn loose(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '_' | '-'))
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// The characters right after and right before `c`, skipping the surrogates.
+fn next_char(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        c => char::from_u32(c as u32 + 1),
+    }
+}
+
+fn previous_char(c: char) -> char {
+    match c {
+        '\u{E000}' => '\u{D7FF}',
+        c => char::from_u32(c as u32 - 1).unwrap(),
+    }
+}
+
+impl fmt::Debug for CharClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for &(start, end) in &self.ranges {
+            if start == end {
+                write!(f, "{}", start.escape_debug())?;
+            } else {
+                write!(f, "{}-{}", start.escape_debug(), end.escape_debug())?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod class_test {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let class = CharClass::new(vec![('x', 'z'), ('a', 'c'), ('b', 'e'), ('f', 'f')]);
+        assert_eq!(class.ranges(), &[('a', 'f'), ('x', 'z')]);
+        assert_eq!(CharClass::new(vec![('b', 'a')]), CharClass::default());
+        assert_eq!(CharClass::from_chars("cab".chars()).ranges(), &[('a', 'c')]);
+    }
+
+    #[test]
+    fn test_contains() {
+        let class = CharClass::new(vec![('a', 'c'), ('x', 'z'), ('é', 'é')]);
+        for c in "abcxyzé".chars() {
+            assert!(class.contains(c), "{} should be in {:?}", c, class);
+        }
+        for c in "dwA{`è".chars() {
+            assert!(!class.contains(c), "{} should not be in {:?}", c, class);
+        }
+        assert!(!CharClass::default().contains('a'));
+    }
+
+    #[test]
+    fn test_is_word_char() {
+        assert!("aZ_9éж".chars().all(is_word_char));
+        assert!(!" -\n".chars().any(is_word_char));
+    }
+
+    #[test]
+    fn test_union() {
+        let left = CharClass::new(vec![('a', 'c'), ('m', 'n')]);
+        let right = CharClass::new(vec![('d', 'f'), ('z', 'z')]);
+        assert_eq!(
+            left.union(&right).ranges(),
+            &[('a', 'f'), ('m', 'n'), ('z', 'z')]
+        );
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let left = CharClass::new(vec![('a', 'f'), ('x', 'z')]);
+        let right = CharClass::new(vec![('d', 'y')]);
+        assert_eq!(
+            left.intersection(&right).ranges(),
+            &[('d', 'f'), ('x', 'y')]
+        );
+        assert_eq!(left.difference(&right).ranges(), &[('a', 'c'), ('z', 'z')]);
+        assert_eq!(right.difference(&left).ranges(), &[('g', 'w')]);
+        assert_eq!(
+            left.symmetric_difference(&right).ranges(),
+            &[('a', 'c'), ('g', 'w'), ('z', 'z')]
+        );
+        assert!(left.intersection(&CharClass::default()).is_empty());
+        assert_eq!(left.difference(&CharClass::default()), left);
+    }
+
+    #[test]
+    fn test_negate() {
+        let class = CharClass::new(vec![('b', 'c'), ('x', 'x')]);
+        assert_eq!(
+            class.negate().ranges(),
+            &[('\0', 'a'), ('d', 'w'), ('y', char::MAX)]
+        );
+        assert_eq!(class.negate().negate(), class);
+        assert_eq!(CharClass::default().negate().ranges(), &[('\0', char::MAX)]);
+        assert_eq!(CharClass::default().negate().negate(), CharClass::default());
+        let surrogates = CharClass::new(vec![('\0', '\u{D7FF}'), ('\u{E000}', char::MAX)]);
+        assert_eq!(surrogates.ranges(), &[('\0', char::MAX)]);
+        let class = CharClass::new(vec![('\u{E000}', '\u{E001}')]);
+        assert_eq!(
+            class.negate().ranges(),
+            &[('\0', '\u{D7FF}'), ('\u{E002}', char::MAX)]
+        );
+    }
+
+    #[test]
+    fn test_perl() {
+        let digit = CharClass::perl('d', true).unwrap();
+        assert!(digit.contains('7') && digit.contains('٣') && !digit.contains('a'));
+        let digit = CharClass::perl('d', false).unwrap();
+        assert!(digit.contains('7') && !digit.contains('٣'));
+        let word = CharClass::perl('w', true).unwrap();
+        assert!("aZ_9éж".chars().all(|c| word.contains(c)));
+        assert!(!word.contains('-') && !word.contains(' '));
+        let word = CharClass::perl('w', false).unwrap();
+        assert!(word.contains('_') && !word.contains('é'));
+        let space = CharClass::perl('s', true).unwrap();
+        assert!(" \t\n\r\u{B}\u{C}\u{A0}\u{2003}"
+            .chars()
+            .all(|c| space.contains(c)));
+        let space = CharClass::perl('s', false).unwrap();
+        assert!(space.contains('\u{B}') && !space.contains('\u{A0}'));
+        assert_eq!(CharClass::perl('x', true), None);
+    }
+
+    #[test]
+    fn test_posix() {
+        let posix = |name| CharClass::posix(name).unwrap();
+        assert!("aZ0".chars().all(|c| posix("alnum").contains(c)));
+        assert!(!posix("alnum").contains('_') && !posix("alpha").contains('é'));
+        assert!(posix("punct").contains('_') && posix("punct").contains('~'));
+        assert!(!posix("punct").contains('a') && !posix("punct").contains(' '));
+        assert!(posix("graph").contains('!') && !posix("graph").contains(' '));
+        assert!(posix("print").contains(' ') && !posix("print").contains('\t'));
+        assert!(posix("cntrl").contains('\u{7F}') && posix("blank").contains('\t'));
+        assert!(posix("xdigit").contains('f') && !posix("xdigit").contains('g'));
+        assert_eq!(posix("space"), CharClass::perl('s', false).unwrap());
+        assert_eq!(posix("digit"), CharClass::perl('d', false).unwrap());
+        assert_eq!(posix("upper").union(&posix("lower")), posix("alpha"));
+        assert_eq!(CharClass::posix("word"), None);
+    }
+
+    #[test]
+    fn test_property() {
+        let property = |name| CharClass::property(name).unwrap();
+        assert!(property("L").contains('é') && !property("L").contains('1'));
+        assert_eq!(property("Lu"), property("Uppercase_Letter"));
+        assert_eq!(property("Lu"), property("uppercase letter"));
+        assert_eq!(property("Lu"), property("gc=Lu"));
+        assert_eq!(property("Lu"), property("General_Category=Lu"));
+        assert!(property("Lu").contains('A') && !property("Lu").contains('a'));
+        assert!(property("Nd").contains('٣'));
+        assert_eq!(property("Nd"), CharClass::perl('d', true).unwrap());
+        assert!(property("Greek").contains('λ') && !property("Greek").contains('l'));
+        assert_eq!(property("Greek"), property("sc=Grek"));
+        assert!(property("Han").contains('語'));
+        assert!(property("Alphabetic").contains('ж') && !property("Alpha").contains('%'));
+        assert_eq!(property("White_Space"), CharClass::perl('s', true).unwrap());
+        assert_eq!(property("Any").ranges(), &[('\0', char::MAX)]);
+        assert_eq!(property("ASCII").ranges(), &[('\0', '\u{7F}')]);
+        assert_eq!(CharClass::property("Foo"), None);
+        assert_eq!(CharClass::property("sc=Lu"), None);
+        assert_eq!(CharClass::property("foo=Lu"), None);
+    }
+
+    #[test]
+    fn test_debug() {
+        let class = CharClass::new(vec![('a', 'c'), ('\n', '\n')]);
+        assert_eq!(format!("{:?}", class), "[\\na-c]");
+    }
+}
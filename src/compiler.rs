@@ -0,0 +1,216 @@
+use crate::ast::{Assertion, Ast, AstKind};
+use crate::class::CharClass;
+use crate::error::ErrorKind::{NestingTooDeep, SizeLimitExceeded};
+use crate::error::RegexError;
+use crate::graph::Graph;
+use crate::nfa::NfaArrow::{self, *};
+use crate::nfa::NFA;
+
+/// Lowers the syntax tree into an NFA. Fails with the span of the first node whose NFA
+/// has more than `size_limit` nodes, before building repetitions that would be too large.
+///
+/// Groups are compiled recursively, so the tree is also checked not to nest more than
+/// `nesting_limit` of them, even though the parser already enforces it on the ones it builds.
+pub fn compile(ast: &Ast, size_limit: usize, nesting_limit: usize) -> Result<NFA, RegexError> {
+    let too_large = || RegexError::new(SizeLimitExceeded, ast.span.clone());
+
+    let nfa = match &ast.kind {
+        AstKind::Empty => Graph::new(0),
+        AstKind::Literal {
+            c,
+            case_insensitive,
+        } => NFA::single(fold(Char(*c), *case_insensitive)),
+        AstKind::Class {
+            class,
+            negated,
+            case_insensitive,
+        } => {
+            let arrow = if *negated {
+                NotOneOf(class.clone())
+            } else {
+                OneOf(class.clone())
+            };
+            NFA::single(fold(arrow, *case_insensitive))
+        }
+        AstKind::Dot {
+            matches_new_line: true,
+            ..
+        } => NFA::single(AnyChar),
+        AstKind::Dot { crlf: true, .. } => {
+            NFA::single(NotOneOf(CharClass::from_chars(['\n', '\r'])))
+        }
+        AstKind::Dot { .. } => NFA::single(Dot),
+        AstKind::Assertion(assertion) => NFA::single(assertion_arrow(*assertion)),
+        AstKind::Group { index, inner } => {
+            let nesting_limit = nesting_limit.checked_sub(1).ok_or_else(|| {
+                RegexError::new(NestingTooDeep, ast.span.start..ast.span.start + 1)
+            })?;
+            let inner = compile(inner, size_limit, nesting_limit)?;
+            match index {
+                Some(index) => inner.group(*index),
+                None => inner,
+            }
+        }
+        AstKind::Repetition {
+            inner,
+            min,
+            max,
+            greedy,
+        } => {
+            let inner = compile(inner, size_limit, nesting_limit)?;
+            // Every repetition is a copy, this is a lower bound of the size of the result.
+            let copies = max.unwrap_or(*min).max(1);
+            if (inner.node_count() - 1).saturating_mul(copies) >= size_limit {
+                return Err(too_large());
+            }
+            inner.repeat(*min, *max, *greedy)
+        }
+        AstKind::Concat(items) => {
+            let mut nfa = Graph::new(0);
+            for item in items {
+                nfa = nfa.concat(compile(item, size_limit, nesting_limit)?);
+                if nfa.node_count() > size_limit {
+                    return Err(too_large());
+                }
+            }
+            nfa
+        }
+        AstKind::Alternation(alternatives) => {
+            // Joined from the right, so that the first alternatives come first in the edges.
+            let mut nfas = alternatives
+                .iter()
+                .map(|alternative| compile(alternative, size_limit, nesting_limit))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut nfa = nfas.pop().unwrap_or_else(|| Graph::new(0));
+            while let Some(left) = nfas.pop() {
+                nfa = left.alternate(nfa);
+            }
+            nfa
+        }
+    };
+
+    if nfa.node_count() > size_limit {
+        return Err(too_large());
+    }
+    Ok(nfa)
+}
+
+fn fold(arrow: NfaArrow, case_insensitive: bool) -> NfaArrow {
+    if case_insensitive {
+        arrow.case_insensitive()
+    } else {
+        arrow
+    }
+}
+
+fn assertion_arrow(assertion: Assertion) -> NfaArrow {
+    match assertion {
+        Assertion::LineStart => LineStart,
+        Assertion::LineEnd => LineEnd,
+        Assertion::MultiLineStart => MultiLineStart,
+        Assertion::MultiLineEnd => MultiLineEnd,
+        Assertion::CrlfLineStart => CrlfLineStart,
+        Assertion::CrlfLineEnd => CrlfLineEnd,
+        Assertion::WordBoundary { unicode } => WordBoundary { unicode },
+        Assertion::NotWordBoundary { unicode } => NotWordBoundary { unicode },
+    }
+}
+
+#[cfg(test)]
+mod compiler_test {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn literal(c: char, at: usize) -> Ast {
+        Ast::new(
+            AstKind::Literal {
+                c,
+                case_insensitive: false,
+            },
+            at..at + 1,
+        )
+    }
+
+    #[test]
+    fn test_compile() {
+        // a|b*
+        let ast = Ast::new(
+            AstKind::Alternation(vec![
+                literal('a', 0),
+                Ast::new(
+                    AstKind::Repetition {
+                        inner: Box::new(literal('b', 2)),
+                        min: 0,
+                        max: None,
+                        greedy: true,
+                    },
+                    2..4,
+                ),
+            ]),
+            0..4,
+        );
+        assert_eq!(
+            compile(&ast, usize::MAX, usize::MAX).unwrap(),
+            NFA::single(Char('a')).alternate(NFA::single(Char('b')).star())
+        );
+    }
+
+    #[test]
+    fn test_compile_group() {
+        let ast = Ast::new(
+            AstKind::Group {
+                index: Some(1),
+                inner: Box::new(Ast::new(AstKind::Empty, 1..1)),
+            },
+            0..2,
+        );
+        assert_eq!(
+            compile(&ast, usize::MAX, usize::MAX).unwrap(),
+            Graph::new(0).group(1)
+        );
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let ast = Ast::new(
+            AstKind::Concat(vec![
+                literal('a', 0),
+                Ast::new(
+                    AstKind::Repetition {
+                        inner: Box::new(literal('b', 1)),
+                        min: 1000,
+                        max: Some(1000),
+                        greedy: true,
+                    },
+                    1..8,
+                ),
+            ]),
+            0..8,
+        );
+        let error = compile(&ast, 100, usize::MAX).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::SizeLimitExceeded);
+        assert_eq!(error.span(), 1..8);
+        let error = compile(&ast, 1001, usize::MAX).unwrap_err();
+        assert_eq!(error.span(), 0..8);
+        assert_eq!(compile(&ast, 1002, usize::MAX).unwrap().node_count(), 1002);
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        // ((a))
+        let group = |inner, span| {
+            Ast::new(
+                AstKind::Group {
+                    index: None,
+                    inner: Box::new(inner),
+                },
+                span,
+            )
+        };
+        let ast = group(group(literal('a', 2), 1..4), 0..5);
+        assert!(compile(&ast, usize::MAX, 2).is_ok());
+        let error = compile(&ast, usize::MAX, 1).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NestingTooDeep);
+        assert_eq!(error.span(), 1..2);
+    }
+}
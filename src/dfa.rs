@@ -1,11 +1,32 @@
 use std::collections::HashMap;
 
-use crate::nfa::{follow_char, initial_state, NFAState, NFA};
+use crate::nfa::{
+    accepted, follow_assertions, follow_char, has_assertions, initial_state, CharKind, NFAState,
+    NFA,
+};
+
+/// The number of states the DFA keeps by default before its cache is cleared.
+pub const DEFAULT_CACHE_SIZE: usize = 10_000;
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct DState {
     nfa_states: NFAState,
+    /// The kind of the character consumed to reach the state, `None` at the start of the text.
+    /// It is only kept when the state has assertions, as they are the only ones that look at it.
+    previous: Option<CharKind>,
     next_states: HashMap<char, usize>,
+    /// The patterns whose final node is reachable before a character,
+    /// by the kind of that character in the order of `CharKind::ALL`.
+    accepts: [Vec<usize>; 5],
+}
+
+impl DState {
+    /// The patterns that match right before `c`.
+    fn accepts_before(&self, c: char) -> &[usize] {
+        let kind = CharKind::of(c);
+        let index = CharKind::ALL.iter().position(|&k| k == kind).unwrap();
+        &self.accepts[index]
+    }
 }
 
 #[derive(Debug)]
@@ -13,69 +34,317 @@ pub struct DFA {
     nfa: NFA,
     pub states: Vec<DState>,
     pub current_state: usize,
+    /// Index of every known state by its sorted NFA nodes and previous character.
+    known_states: HashMap<(Vec<usize>, Option<CharKind>), usize>,
+    anchored: bool,
+    cache_size: usize,
 }
 impl DFA {
     pub fn new(nfa: NFA) -> Self {
-        let init = DState {
-            nfa_states: initial_state(&nfa),
-            next_states: HashMap::new(),
-        };
-        Self {
+        let mut dfa = Self {
             nfa,
-            states: vec![init],
+            states: vec![],
             current_state: 0,
-        }
+            known_states: HashMap::new(),
+            anchored: false,
+            cache_size: DEFAULT_CACHE_SIZE,
+        };
+        dfa.clear_cache();
+        dfa
+    }
+
+    /// Only look for matches that start at the beginning of the text.
+    pub fn anchored(mut self, anchored: bool) -> Self {
+        self.anchored = anchored;
+        self
+    }
+
+    /// The number of states to keep before the cache is cleared. States are built lazily
+    /// while walking, so without a limit some patterns could build exponentially many of them.
+    /// At least three states are kept: the initial one, the current one and the next one.
+    pub fn cache_size(mut self, cache_size: usize) -> Self {
+        self.cache_size = cache_size.max(3);
+        self
     }
 
-    pub fn walk(&mut self, text: String) -> bool {
-        for (i, c) in text.chars().enumerate() {
-            if self.get_current_states().contains(&self.nfa.final_node) {
+    pub fn walk(&mut self, text: &str) -> bool {
+        self.current_state = 0;
+
+        for c in text.chars() {
+            if !self.get_current_state().accepts_before(c).is_empty() {
                 return true;
             }
 
-            self.next(c, i == text.len() - 1);
+            self.next(c);
 
             if self.get_current_states().is_empty() {
                 return false;
             }
         }
 
-        self.get_current_states().contains(&self.nfa.final_node)
+        !self.accepts_at_end().is_empty()
     }
 
-    fn next(&mut self, c: char, is_last: bool) {
-        let next_index = self.states.len();
-        let current_state = self.states.get_mut(self.current_state).unwrap();
-        match current_state.next_states.get(&c) {
-            None => {
-                let next_states = follow_char(&self.nfa, &current_state.nfa_states, c, is_last);
-                let state = DState {
-                    nfa_states: next_states,
-                    next_states: HashMap::new(),
-                };
-                current_state.next_states.insert(c, next_index);
-                self.states.push(state);
-                self.current_state = next_index;
+    /// Returns for every pattern of an NFA made by `NFA::union` whether it matches
+    /// somewhere in the text. The text is scanned once for all the patterns.
+    pub fn walk_all(&mut self, text: &str) -> Vec<bool> {
+        self.current_state = 0;
+        let mut matched = vec![false; self.nfa.final_nodes().len()];
+        let mut remaining = matched.len();
+        let mut record = |patterns: &[usize]| {
+            for &i in patterns {
+                if !matched[i] {
+                    matched[i] = true;
+                    remaining -= 1;
+                }
+            }
+            remaining == 0
+        };
+
+        for c in text.chars() {
+            if record(self.get_current_state().accepts_before(c)) {
+                return matched;
+            }
+
+            self.next(c);
+
+            if self.get_current_states().is_empty() {
+                return matched;
+            }
+        }
+
+        record(&self.accepts_at_end());
+        matched
+    }
+
+    /// Returns the longest non-empty match at the start of the text as the pattern it belongs to
+    /// and its end, preferring the first pattern when several match the same text.
+    /// The DFA has to be anchored for the match to start at the beginning of the text.
+    pub fn longest_match(&mut self, text: &str) -> Option<(usize, usize)> {
+        self.current_state = 0;
+        let mut longest = None;
+
+        for (i, c) in text.char_indices() {
+            let accepts = self.get_current_state().accepts_before(c);
+            if let Some(&pattern) = accepts.first().filter(|_| i > 0) {
+                longest = Some((pattern, i));
+            }
+
+            self.next(c);
+
+            if self.get_current_states().is_empty() {
+                return longest;
             }
-            Some(&d) => self.current_state = d,
         }
+
+        match self.accepts_at_end().first() {
+            Some(&pattern) if !text.is_empty() => Some((pattern, text.len())),
+            _ => longest,
+        }
+    }
+
+    /// The end of the text is not cached as a transition, otherwise a state reached
+    /// through `$` would leak into walks where the same character is not the last one.
+    fn accepts_at_end(&self) -> Vec<usize> {
+        let current = self.get_current_state();
+        let end = follow_assertions(
+            &self.nfa,
+            current.nfa_states.clone(),
+            current.previous,
+            None,
+        );
+        accepted(&self.nfa, &end)
+    }
+
+    fn next(&mut self, c: char) {
+        if let Some(&d) = self.get_current_state().next_states.get(&c) {
+            self.current_state = d;
+            return;
+        }
+
+        let current = self.get_current_state();
+        let next_states = follow_char(
+            &self.nfa,
+            &current.nfa_states,
+            current.previous,
+            c,
+            !self.anchored,
+        );
+        if self.states.len() >= self.cache_size {
+            // Only the initial state and the one we are in are needed to continue.
+            let current = self.get_current_state();
+            let (states, previous) = (current.nfa_states.clone(), current.previous);
+            self.clear_cache();
+            self.current_state = self.add_state(states, previous);
+        }
+
+        let next_index = self.add_state(next_states, Some(CharKind::of(c)));
+        let current_state = self.states.get_mut(self.current_state).unwrap();
+        current_state.next_states.insert(c, next_index);
+        self.current_state = next_index;
+    }
+
+    fn add_state(&mut self, nfa_states: NFAState, previous: Option<CharKind>) -> usize {
+        // States without assertions behave the same whatever the previous character.
+        let previous = previous.filter(|_| has_assertions(&self.nfa, &nfa_states));
+        let mut nodes: Vec<_> = nfa_states.iter().cloned().collect();
+        nodes.sort_unstable();
+        let key = (nodes, previous);
+        if let Some(&index) = self.known_states.get(&key) {
+            return index;
+        }
+
+        let accepts = CharKind::ALL.map(|next| {
+            let before = follow_assertions(&self.nfa, nfa_states.clone(), previous, Some(next));
+            accepted(&self.nfa, &before)
+        });
+        self.states.push(DState {
+            nfa_states,
+            previous,
+            next_states: HashMap::new(),
+            accepts,
+        });
+        self.known_states.insert(key, self.states.len() - 1);
+        self.states.len() - 1
+    }
+
+    fn clear_cache(&mut self) {
+        self.states.clear();
+        self.known_states.clear();
+        let init = initial_state(&self.nfa);
+        self.add_state(init, None);
+    }
+
+    fn get_current_state(&self) -> &DState {
+        self.states.get(self.current_state).unwrap()
     }
 
     fn get_current_states(&self) -> &NFAState {
-        &self.states.get(self.current_state).unwrap().nfa_states
+        &self.get_current_state().nfa_states
     }
 }
 
 #[cfg(test)]
 mod dfa_test {
+    use crate::error::RegexError;
     use crate::{parser, regex_tests};
 
     use super::*;
 
-    fn check_for_pattern(pattern: &str, string: &str) -> Result<bool, String> {
-        let nfa = parser::parse(pattern)?;
-        Ok(DFA::new(nfa).walk(string.to_string()))
+    fn check_for_pattern(pattern: &str, string: &str) -> Result<bool, RegexError> {
+        let nfa = parser::parse(pattern)?.nfa;
+        Ok(DFA::new(nfa).walk(string))
     }
 
     regex_tests!(check_for_pattern);
+
+    #[test]
+    fn test_states_are_reused() {
+        let mut dfa = DFA::new(parser::parse("ab").unwrap().nfa);
+        assert!(!dfa.walk("cccccccccccccccccccc"));
+        assert_eq!(dfa.states.len(), 1);
+        assert!(dfa.walk("cacacab"));
+        assert_eq!(dfa.states.len(), 3);
+    }
+
+    #[test]
+    fn test_cache_size() {
+        let mut dfa = DFA::new(parser::parse("a[bc]*d").unwrap().nfa).cache_size(3);
+        assert!(dfa.walk("xxabcbcbd"));
+        assert!(dfa.states.len() <= 3);
+        assert!(!dfa.walk("xxabcbcb"));
+        assert!(dfa.walk("ad"));
+    }
+
+    #[test]
+    fn test_anchored() {
+        let mut dfa = DFA::new(parser::parse("ab").unwrap().nfa).anchored(true);
+        assert!(dfa.walk("abc"));
+        assert!(!dfa.walk("cab"));
+    }
+
+    #[test]
+    fn test_multi_line() {
+        let flags = parser::Flags {
+            multi_line: true,
+            ..parser::Flags::default()
+        };
+        let mut dfa = DFA::new(parser::parse_with_flags("^b$", flags).unwrap().nfa);
+        assert!(dfa.walk("a\nb\nc"));
+        assert!(dfa.walk("a\nb"));
+        assert!(dfa.walk("b\nc"));
+        assert!(!dfa.walk("ab\nc"));
+        assert!(!dfa.walk("a\nbc"));
+    }
+
+    #[test]
+    fn test_crlf() {
+        let flags = parser::Flags {
+            multi_line: true,
+            crlf: true,
+            ..parser::Flags::default()
+        };
+        let mut dfa = DFA::new(parser::parse_with_flags("^b?$", flags).unwrap().nfa).anchored(true);
+        assert_eq!(dfa.longest_match("b\r\nc"), Some((0, 1)));
+        let mut dfa = DFA::new(parser::parse_with_flags("^$", flags).unwrap().nfa);
+        assert!(!dfa.walk("a\r\nb\r\nc"));
+        assert!(dfa.walk("a\r\n\r\nc"));
+        assert!(dfa.walk("a\r\rc"));
+    }
+
+    #[test]
+    fn test_word_boundary_states() {
+        let mut dfa = DFA::new(parser::parse("\\bab").unwrap().nfa);
+        assert!(dfa.walk("x ab"));
+        assert!(!dfa.walk("xab"));
+        assert!(dfa.walk("ab"));
+        // The same NFA nodes after a word character and after a space are different states.
+        let states = dfa.states.len();
+        assert!(!dfa.walk("xaxaxa"));
+        assert!(dfa.walk(" ab"));
+        assert_eq!(dfa.states.len(), states);
+    }
+
+    #[test]
+    fn test_walk_all() {
+        let patterns = ["ab", "b+c", "^c", "d$", "x"];
+        let nfa = NFA::union(
+            patterns
+                .iter()
+                .map(|p| parser::parse(p).unwrap().nfa)
+                .collect(),
+        );
+        let mut dfa = DFA::new(nfa);
+        assert_eq!(dfa.walk_all("abbcd"), vec![true, true, false, true, false]);
+        assert_eq!(dfa.walk_all("cab"), vec![true, false, true, false, false]);
+        assert_eq!(dfa.walk_all("dc"), vec![false; 5]);
+        assert!(dfa.walk("dc x"));
+        assert!(!dfa.walk("dc"));
+    }
+
+    #[test]
+    fn test_longest_match() {
+        let patterns = ["a", "ab+", "[ab]+", "c", "x*"];
+        let nfa = NFA::union(
+            patterns
+                .iter()
+                .map(|p| parser::parse(p).unwrap().nfa)
+                .collect(),
+        );
+        let mut dfa = DFA::new(nfa).anchored(true);
+        assert_eq!(dfa.longest_match("a"), Some((0, 1)));
+        assert_eq!(dfa.longest_match("abbc"), Some((1, 3)));
+        assert_eq!(dfa.longest_match("abba"), Some((2, 4)));
+        assert_eq!(dfa.longest_match("cab"), Some((3, 1)));
+        assert_eq!(dfa.longest_match("éa"), None);
+        assert_eq!(dfa.longest_match(""), None);
+    }
+
+    #[test]
+    fn test_reuse_between_walks() {
+        let mut dfa = DFA::new(parser::parse("ab$").unwrap().nfa);
+        assert!(dfa.walk("ab"));
+        assert!(!dfa.walk("abc"));
+        assert!(dfa.walk("cab"));
+    }
 }
@@ -0,0 +1,205 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+/// What is wrong with a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// `(` without a matching `)`.
+    UnclosedGroup,
+    /// `)` without a matching `(`.
+    UnopenedGroup,
+    /// A quantifier with nothing to repeat, like in `*a` or `a**`.
+    DanglingQuantifier,
+    /// `[` without a matching `]`.
+    UnclosedClass,
+    /// A character class that matches nothing, like `[]`.
+    EmptyClass,
+    /// A range in a character class whose end comes before its start, like `[z-a]`.
+    InvalidClassRange,
+    /// A set operation in a character class without a class on one of its sides, like `[a&&]`.
+    MissingClassOperand,
+    /// A character that can not be escaped, only punctuation, space, control escapes like `\n`
+    /// and the escapes of character classes are allowed.
+    BadEscape,
+    /// `\` at the end of the pattern.
+    TrailingEscape,
+    /// `\x` or `\u` not followed by a well-formed hexadecimal code point:
+    /// two digits after `\x`, or one to six digits in braces.
+    InvalidHexEscape,
+    /// `\x{` or `\u{` without a matching `}`.
+    UnclosedHexEscape,
+    /// A hexadecimal escape above `10FFFF` or in the surrogate range.
+    InvalidCodePoint,
+    /// A POSIX class like `[:alpha:]` with an unknown name.
+    UnknownPosixClass,
+    /// `\p` or `\P` without a class name, or with `{` and no matching `}`.
+    UnclosedProperty,
+    /// `\p` or `\P` with a name that is not a known Unicode class.
+    UnknownProperty,
+    /// `{` without a matching `}`.
+    UnclosedRepetition,
+    /// A counted repetition that is not `{n}`, `{n,}` or `{n,m}`.
+    InvalidRepetition,
+    /// A counted repetition with a maximum lower than its minimum, like `{3,1}`.
+    InvalidRepetitionRange,
+    /// A counted repetition with a count above the configured limit.
+    RepetitionTooLarge,
+    /// `(?` followed by something that is not a known group flag.
+    UnknownGroupFlag,
+    /// Inline flags without any flag, or with nothing after `-`, like `(?)` or `(?i-)`.
+    MissingGroupFlag,
+    /// A group name that is empty or is not made of letters, digits and `_`.
+    InvalidGroupName,
+    /// A group name without the closing `>`.
+    UnclosedGroupName,
+    /// Two groups with the same name.
+    DuplicateGroupName,
+    /// The pattern compiles to an NFA with more nodes than allowed.
+    SizeLimitExceeded,
+    /// Groups or character classes nested deeper than the configured limit.
+    NestingTooDeep,
+}
+
+impl ErrorKind {
+    fn description(&self) -> &'static str {
+        match self {
+            ErrorKind::UnclosedGroup => "unclosed group",
+            ErrorKind::UnopenedGroup => "unopened group",
+            ErrorKind::DanglingQuantifier => "quantifier without anything to repeat",
+            ErrorKind::UnclosedClass => "unclosed character class",
+            ErrorKind::EmptyClass => "empty character class",
+            ErrorKind::InvalidClassRange => {
+                "invalid character class range, the start is after the end"
+            }
+            ErrorKind::MissingClassOperand => "missing operand of a character class operation",
+            ErrorKind::BadEscape => "unrecognized escape sequence",
+            ErrorKind::TrailingEscape => "incomplete escape sequence at the end of the pattern",
+            ErrorKind::InvalidHexEscape => "invalid hexadecimal escape sequence",
+            ErrorKind::UnclosedHexEscape => "unclosed hexadecimal escape sequence",
+            ErrorKind::InvalidCodePoint => "escape sequence is not a valid Unicode code point",
+            ErrorKind::UnknownPosixClass => "unknown POSIX class name",
+            ErrorKind::UnclosedProperty => "unclosed Unicode class name",
+            ErrorKind::UnknownProperty => "unknown Unicode class",
+            ErrorKind::UnclosedRepetition => "unclosed counted repetition",
+            ErrorKind::InvalidRepetition => "invalid counted repetition",
+            ErrorKind::InvalidRepetitionRange => {
+                "invalid counted repetition range, the minimum is above the maximum"
+            }
+            ErrorKind::RepetitionTooLarge => "counted repetition exceeds the repetition limit",
+            ErrorKind::UnknownGroupFlag => "unrecognized group flag",
+            ErrorKind::MissingGroupFlag => "expected a group flag",
+            ErrorKind::InvalidGroupName => "invalid group name",
+            ErrorKind::UnclosedGroupName => "unclosed group name",
+            ErrorKind::DuplicateGroupName => "duplicate group name",
+            ErrorKind::SizeLimitExceeded => "compiled regex exceeds the size limit",
+            ErrorKind::NestingTooDeep => "nesting exceeds the nesting limit",
+        }
+    }
+}
+
+/// An error in a pattern, with the byte span of the part of the pattern that caused it.
+///
+/// It is displayed with the pattern and a caret under the offending part:
+///
+/// ```text
+/// regex parse error:
+///     a**
+///       ^
+/// error: quantifier without anything to repeat
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegexError {
+    kind: ErrorKind,
+    span: Range<usize>,
+    pattern: String,
+}
+
+impl RegexError {
+    /// Creates an error for a pattern that is filled in later with `with_pattern`,
+    /// so that the parser does not need to carry the whole pattern around.
+    pub(crate) fn new(kind: ErrorKind, span: Range<usize>) -> Self {
+        Self {
+            kind,
+            span,
+            pattern: String::new(),
+        }
+    }
+
+    pub(crate) fn with_pattern(mut self, pattern: &str) -> Self {
+        self.pattern = pattern.to_string();
+        self
+    }
+
+    pub(crate) fn with_span(mut self, span: Range<usize>) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The byte span of the offending part of the pattern.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Only the line with the start of the span is shown, the pattern can span several lines.
+        let start = self.span.start.min(self.pattern.len());
+        let line_start = self.pattern[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.pattern[start..]
+            .find('\n')
+            .map_or(self.pattern.len(), |i| start + i);
+        let end = self.span.end.clamp(start, line_end);
+
+        let indent = self.pattern[line_start..start].chars().count();
+        let width = self.pattern[start..end].chars().count().max(1);
+
+        writeln!(f, "regex parse error:")?;
+        writeln!(f, "    {}", &self.pattern[line_start..line_end])?;
+        writeln!(f, "    {}{}", " ".repeat(indent), "^".repeat(width))?;
+        write!(f, "error: {}", self.kind.description())
+    }
+}
+
+impl Error for RegexError {}
+
+#[cfg(test)]
+mod error_test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let error = RegexError::new(ErrorKind::DanglingQuantifier, 2..3).with_pattern("a**");
+        assert_eq!(
+            error.to_string(),
+            "regex parse error:\n    a**\n      ^\nerror: quantifier without anything to repeat"
+        );
+    }
+
+    #[test]
+    fn test_display_span() {
+        let error = RegexError::new(ErrorKind::InvalidGroupName, 6..9).with_pattern("é(?P<1é>a)");
+        assert_eq!(
+            error.to_string(),
+            "regex parse error:\n    é(?P<1é>a)\n         ^^\nerror: invalid group name"
+        );
+    }
+
+    #[test]
+    fn test_display_end_of_pattern() {
+        let error = RegexError::new(ErrorKind::UnclosedClass, 3..3).with_pattern("ab[");
+        assert_eq!(
+            error.to_string(),
+            "regex parse error:\n    ab[\n       ^\nerror: unclosed character class"
+        );
+    }
+}
@@ -3,11 +3,15 @@ use std::fmt;
 
 #[derive(Clone, PartialEq, Eq)]
 /// Basic domain-specific implementation of a graph.
-/// It is assumed that there is only one Initial node (at index 0) and one Final node.
+/// It is assumed that there is only one Initial node (at index 0). Graphs are built from
+/// fragments with one Final node, only `Graph::parallel` joins them into a graph
+/// with several accepting nodes.
 pub struct Graph<Arrow> {
     pub edges: HashMap<Node, Vec<Edge<Arrow>>>,
     node_count: usize,
     pub final_node: usize,
+    /// Accepting nodes of a graph made by `Graph::parallel`, one per joined graph.
+    accepting: Vec<Node>,
 }
 
 impl<Arrow: PartialEq> Graph<Arrow> {
@@ -16,6 +20,39 @@ impl<Arrow: PartialEq> Graph<Arrow> {
             edges: HashMap::new(),
             node_count: final_node + 1,
             final_node,
+            accepting: vec![],
+        }
+    }
+
+    /// Joins the graphs side by side from a shared initial node. Each of them keeps
+    /// its own accepting node, found at its index in `final_nodes`.
+    /// The graphs must not be empty, their final node can not be the initial one.
+    pub fn parallel<I: IntoIterator<Item = Self>>(graphs: I) -> Self {
+        let mut graph = Self::new(0);
+        let mut accepting = vec![];
+
+        for other in graphs {
+            let to = graph.node_count;
+            graph.node_count += 1;
+            graph = graph.attach_parallel(other, 0, to);
+            accepting.push(to);
+        }
+
+        graph.accepting = accepting;
+        graph
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    /// The accepting nodes, ordered as the graphs given to `Graph::parallel`.
+    /// Any other graph only accepts in its final node.
+    pub fn final_nodes(&self) -> &[Node] {
+        if self.accepting.is_empty() {
+            std::slice::from_ref(&self.final_node)
+        } else {
+            &self.accepting
         }
     }
 
@@ -137,6 +174,7 @@ mod test {
         let expected = Graph {
             node_count: 5,
             final_node: 4,
+            accepting: vec![],
             edges: hash(vec![
                 (0, vec![edge('a', 1)]),
                 (1, vec![edge('b', 2)]),
@@ -162,6 +200,7 @@ mod test {
         let expected = Graph {
             node_count: 7,
             final_node: 4,
+            accepting: vec![],
             edges: hash(vec![
                 (0, vec![edge('a', 1)]),
                 (1, vec![edge('b', 2), edge('e', 5)]),
@@ -174,6 +213,26 @@ mod test {
         assert_eq!(g1.attach_parallel(g2, 1, 3), expected);
     }
 
+    #[test]
+    fn test_parallel() {
+        let g1 = Graph::new(2).add_edge(0, 'a', 1).add_edge(1, 'b', 2);
+        let g2 = Graph::new(1).add_edge(0, 'c', 1);
+        let g3 = Graph::new(2).add_edge(0, 'd', 1).add_edge(1, 'e', 2);
+
+        let graph = Graph::parallel(vec![g1, g2, g3]);
+        assert_eq!(
+            graph.edges,
+            hash(vec![
+                (0, vec![edge('a', 2), edge('c', 3), edge('d', 5)]),
+                (2, vec![edge('b', 1)]),
+                (5, vec![edge('e', 4)]),
+            ])
+        );
+        assert_eq!(graph.final_nodes(), &[1, 3, 4]);
+        assert_eq!(graph.node_count(), 6);
+        assert_eq!(Graph::new(2).add_edge(0, 'a', 2).final_nodes(), &[2]);
+    }
+
     fn edge(ch: char, to: usize) -> super::Edge<char> {
         super::Edge { ch, to }
     }
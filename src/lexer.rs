@@ -0,0 +1,348 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::builder::DEFAULT_NFA_SIZE_LIMIT;
+use crate::dfa::DFA;
+use crate::error::RegexError;
+use crate::nfa::NFA;
+use crate::parser::{self, Flags};
+
+/// Splits a text into tokens described by a list of `(kind, pattern)` rules.
+///
+/// At every position the rule with the longest match wins (maximal munch),
+/// and when several rules match the same text the first one is chosen.
+/// All the rules are joined into one automaton in which each of them keeps its own
+/// accepting node, so a token is found in a single pass whatever the number of rules.
+/// Rules never produce empty tokens, and as every token is matched on its own,
+/// the start of every token is the start of the text: `^` and `\A` match there,
+/// and `\b` and `\B` see no character before it, so `\bx` matches the `x` of `ax`
+/// when `a` is lexed as a token of its own.
+///
+/// ```
+/// use re::Lexer;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Kind {
+///     Let,
+///     Ident,
+///     Space,
+/// }
+///
+/// let lexer = Lexer::new(vec![
+///     (Kind::Let, "let"),
+///     (Kind::Ident, "[abelt]+"),
+///     (Kind::Space, " +"),
+/// ])
+/// .unwrap();
+/// let kinds: Vec<_> = lexer.tokens("let lettle").map(|t| t.unwrap().kind()).collect();
+/// assert_eq!(kinds, vec![Kind::Let, Kind::Space, Kind::Ident]);
+/// ```
+pub struct Lexer<K> {
+    kinds: Vec<K>,
+    patterns: Vec<String>,
+    dfa: Mutex<DFA>,
+}
+
+impl<K: Clone> Lexer<K> {
+    /// Compiles the rules, the first invalid pattern is reported as the error.
+    /// The order of the rules is their priority. Each pattern is held to the default
+    /// NFA size limit of a `Regex`.
+    pub fn new<I, S>(rules: I) -> Result<Self, RegexError>
+    where
+        I: IntoIterator<Item = (K, S)>,
+        S: AsRef<str>,
+    {
+        let mut kinds = vec![];
+        let mut patterns = vec![];
+        let mut nfas = vec![];
+        for (kind, pattern) in rules {
+            let pattern = pattern.as_ref();
+            nfas.push(
+                parser::parse_with_limit(pattern, Flags::default(), DEFAULT_NFA_SIZE_LIMIT)?.nfa,
+            );
+            kinds.push(kind);
+            patterns.push(pattern.to_string());
+        }
+
+        Ok(Self {
+            kinds,
+            patterns,
+            dfa: Mutex::new(DFA::new(NFA::union(nfas)).anchored(true)),
+        })
+    }
+
+    /// Returns an iterator over the tokens of the text.
+    ///
+    /// When no rule matches, an error is returned with the position of the first character
+    /// that can not be lexed, and lexing resumes after that character.
+    pub fn tokens<'l, 't>(&'l self, text: &'t str) -> Tokens<'l, 't, K> {
+        Tokens {
+            lexer: self,
+            text,
+            position: 0,
+        }
+    }
+
+    /// Returns the patterns of the rules in order.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Returns the rule and the end of the longest token at the start of the text.
+    fn longest_match(&self, text: &str) -> Option<(usize, usize)> {
+        if self.kinds.is_empty() {
+            return None;
+        }
+        self.lock().longest_match(text)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, DFA> {
+        match self.dfa.lock() {
+            Ok(dfa) => dfa,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl<K> fmt::Debug for Lexer<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Lexer({:?})", self.patterns)
+    }
+}
+
+/// A token found by a `Lexer`, with its kind and its byte offsets in the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'t, K> {
+    kind: K,
+    text: &'t str,
+    start: usize,
+    end: usize,
+}
+
+impl<'t, K: Clone> Token<'t, K> {
+    /// Returns the kind of the rule that matched the token.
+    pub fn kind(&self) -> K {
+        self.kind.clone()
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn as_str(&self) -> &'t str {
+        &self.text[self.start..self.end]
+    }
+}
+
+/// A position in the text where no rule of a `Lexer` matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LexError {
+    position: usize,
+    found: char,
+}
+
+impl LexError {
+    /// Returns the byte offset of the character that can not be lexed.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the character that can not be lexed.
+    pub fn found(&self) -> char {
+        self.found
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no token matches {:?} at byte {}",
+            self.found, self.position
+        )
+    }
+}
+
+impl Error for LexError {}
+
+/// Iterator over the tokens of a text, see `Lexer::tokens`.
+#[derive(Debug)]
+pub struct Tokens<'l, 't, K> {
+    lexer: &'l Lexer<K>,
+    text: &'t str,
+    position: usize,
+}
+
+impl<'l, 't, K: Clone> Iterator for Tokens<'l, 't, K> {
+    type Item = Result<Token<'t, K>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.position;
+        let rest = &self.text[start..];
+        let found = rest.chars().next()?;
+
+        match self.lexer.longest_match(rest) {
+            Some((rule, len)) => {
+                self.position += len;
+                Some(Ok(Token {
+                    kind: self.lexer.kinds[rule].clone(),
+                    text: self.text,
+                    start,
+                    end: self.position,
+                }))
+            }
+            None => {
+                self.position += found.len_utf8();
+                Some(Err(LexError {
+                    position: start,
+                    found,
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod lexer_test {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Kind {
+        If,
+        Ident,
+        Number,
+        Op,
+        Space,
+    }
+    use Kind::*;
+
+    fn lexer() -> Lexer<Kind> {
+        Lexer::new(vec![
+            (If, "if"),
+            (Ident, "[a-z_]+"),
+            (Number, "[0-9]+(\\.[0-9]+)?"),
+            (Op, "=|==|<|<=|\\+"),
+            (Space, "[ \n]+"),
+        ])
+        .unwrap()
+    }
+
+    fn lex(text: &str) -> Vec<Result<(Kind, &str), usize>> {
+        lexer()
+            .tokens(text)
+            .map(|t| match t {
+                Ok(t) => Ok((t.kind(), t.as_str())),
+                Err(e) => Err(e.position()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            lex("if x <= 12.5"),
+            vec![
+                Ok((If, "if")),
+                Ok((Space, " ")),
+                Ok((Ident, "x")),
+                Ok((Space, " ")),
+                Ok((Op, "<=")),
+                Ok((Space, " ")),
+                Ok((Number, "12.5")),
+            ]
+        );
+        assert_eq!(lex(""), vec![]);
+    }
+
+    #[test]
+    fn test_maximal_munch() {
+        assert_eq!(lex("iffy"), vec![Ok((Ident, "iffy"))]);
+        assert_eq!(
+            lex("a==b"),
+            vec![Ok((Ident, "a")), Ok((Op, "==")), Ok((Ident, "b"))]
+        );
+        // The longest match wins even if a longer attempt fails halfway.
+        assert_eq!(lex("12."), vec![Ok((Number, "12")), Err(2)]);
+    }
+
+    #[test]
+    fn test_priority() {
+        assert_eq!(lex("if"), vec![Ok((If, "if"))]);
+        let lexer = Lexer::new(vec![(Ident, "[ab]+"), (If, "ab")]).unwrap();
+        let kinds: Vec<_> = lexer.tokens("ab").map(|t| t.unwrap().kind()).collect();
+        assert_eq!(kinds, vec![Ident]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            lex("a?é+1"),
+            vec![
+                Ok((Ident, "a")),
+                Err(1),
+                Err(2),
+                Ok((Op, "+")),
+                Ok((Number, "1"))
+            ]
+        );
+        let error = lexer().tokens("é").next().unwrap().unwrap_err();
+        assert_eq!(error.found(), 'é');
+        assert_eq!(error.to_string(), "no token matches 'é' at byte 0");
+    }
+
+    #[test]
+    fn test_spans() {
+        let spans: Vec<_> = lexer()
+            .tokens("é1 x")
+            .filter_map(|t| t.ok())
+            .map(|t| t.range())
+            .collect();
+        assert_eq!(spans, vec![2..3, 3..4, 4..5]);
+    }
+
+    #[test]
+    fn test_empty_matches_are_ignored() {
+        let lexer = Lexer::new(vec![(Space, " *"), (Ident, "a")]).unwrap();
+        let tokens: Vec<_> = lexer.tokens("a  a").map(|t| t.unwrap().as_str()).collect();
+        assert_eq!(tokens, vec!["a", "  ", "a"]);
+    }
+
+    #[test]
+    fn test_token_start_is_text_start() {
+        let lexer = Lexer::new(vec![(Ident, "a"), (If, "\\bx"), (Op, "^\\+")]).unwrap();
+        let kinds: Vec<_> = lexer.tokens("ax+").map(|t| t.unwrap().kind()).collect();
+        assert_eq!(kinds, vec![Ident, If, Op]);
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let error = Lexer::new(vec![(If, "if"), (Ident, "((a{1000}){1000}){1000}")])
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), crate::error::ErrorKind::SizeLimitExceeded);
+    }
+
+    #[test]
+    fn test_invalid_rule() {
+        let error = Lexer::new(vec![(If, "if"), (Op, "+")]).unwrap_err();
+        assert_eq!(error.pattern(), "+");
+        let empty = Lexer::new(Vec::<(Kind, &str)>::new()).unwrap();
+        assert_eq!(
+            empty.tokens("a").next(),
+            Some(Err(LexError {
+                position: 0,
+                found: 'a'
+            }))
+        );
+    }
+}
@@ -0,0 +1,27 @@
+#![allow(clippy::upper_case_acronyms)]
+
+mod ast;
+mod builder;
+mod class;
+mod compiler;
+mod dfa;
+mod error;
+mod graph;
+mod lexer;
+mod nfa;
+mod parser;
+mod pike;
+mod regex;
+mod replace;
+mod set;
+mod unicode_tables;
+
+#[cfg(test)]
+mod test;
+
+pub use crate::builder::RegexBuilder;
+pub use crate::error::{ErrorKind, RegexError};
+pub use crate::lexer::{LexError, Lexer, Token, Tokens};
+pub use crate::regex::{CaptureMatches, Captures, Match, Matches, Regex, Split, SplitN};
+pub use crate::replace::{Replacer, Template};
+pub use crate::set::{RegexSet, SetMatches, SetMatchesIter};
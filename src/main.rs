@@ -1,23 +1,18 @@
-mod dfa;
-mod graph;
-mod nfa;
-mod parser;
+use re::Regex;
+use std::{env, process};
 
-#[cfg(test)]
-mod test;
-
-use crate::dfa::DFA;
-use std::env;
-
-fn main() -> Result<(), String> {
+fn main() {
     let args: Vec<String> = env::args().collect();
 
     if let [_, pattern, string] = args.as_slice() {
-        let graph = parser::parse(pattern.as_str())?;
-        println!("{:?}", DFA::new(graph).walk(string.to_owned()));
+        match Regex::new(pattern) {
+            Ok(regex) => println!("{:?}", regex.is_match(string)),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
     } else {
         println!("Usage: re <pattern> <string>")
     }
-
-    Ok(())
 }
@@ -1,4 +1,6 @@
 use super::graph::{Edge, Graph};
+use crate::class::{is_word_char, CharClass};
+use crate::unicode_tables::CASE_FOLDING;
 
 use std::collections::HashSet;
 use NfaArrow::*;
@@ -9,52 +11,393 @@ pub type NFAState = HashSet<usize>;
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum NfaArrow {
     Epsilon,
+    /// Epsilon arrow that goes back to the start of a loop for another iteration.
+    Repeat,
+    /// Epsilon arrow that records the current position in a capture slot.
+    Save(usize),
     Char(char),
-    OneOf(Vec<char>),
-    NotOneOf(Vec<char>),
+    OneOf(CharClass),
+    NotOneOf(CharClass),
+    /// Any character except `\n`.
     Dot,
+    AnyChar,
+    /// Start of the text.
     LineStart,
+    /// End of the text.
     LineEnd,
+    /// Start of the text or right after `\n`.
+    MultiLineStart,
+    /// End of the text or right before `\n`.
+    MultiLineEnd,
+    /// Start of the text, or right after `\n` or `\r` but not between `\r` and `\n`.
+    CrlfLineStart,
+    /// End of the text, or right before `\n` or `\r` but not between `\r` and `\n`.
+    CrlfLineEnd,
+    /// Between a word character and a character that is not one, or the start or end of the text.
+    /// Without `unicode` only ASCII characters are word characters.
+    WordBoundary {
+        unicode: bool,
+    },
+    /// Anywhere a `WordBoundary` does not match.
+    NotWordBoundary {
+        unicode: bool,
+    },
 }
 
+/// The kind of a character next to a position of the text,
+/// which is all that the assertions need to know about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CharKind {
+    LineBreak,
+    CarriageReturn,
+    AsciiWord,
+    /// A word character that is not ASCII.
+    Word,
+    Other,
+}
+
+impl CharKind {
+    pub const ALL: [CharKind; 5] = [
+        CharKind::LineBreak,
+        CharKind::CarriageReturn,
+        CharKind::AsciiWord,
+        CharKind::Word,
+        CharKind::Other,
+    ];
+
+    pub fn of(c: char) -> Self {
+        if c == '\n' {
+            CharKind::LineBreak
+        } else if c == '\r' {
+            CharKind::CarriageReturn
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            CharKind::AsciiWord
+        } else if !c.is_ascii() && is_word_char(c) {
+            CharKind::Word
+        } else {
+            CharKind::Other
+        }
+    }
+}
+
+/// `None` stands for the start or the end of the text, which are not words.
+fn is_word(kind: Option<CharKind>, unicode: bool) -> bool {
+    match kind {
+        Some(CharKind::AsciiWord) => true,
+        Some(CharKind::Word) => unicode,
+        _ => false,
+    }
+}
+
+impl NfaArrow {
+    /// Returns true if the arrow can be followed by consuming `c`.
+    pub fn accepts(&self, c: char) -> bool {
+        match self {
+            Char(ch) => c == *ch,
+            Dot => c != '\n',
+            AnyChar => true,
+            OneOf(class) => class.contains(c),
+            NotOneOf(class) => !class.contains(c),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the arrow can always be followed without consuming anything.
+    pub fn is_epsilon(&self) -> bool {
+        matches!(self, Epsilon | Repeat | Save(_))
+    }
+
+    /// Returns true if the arrow can only be followed without consuming anything
+    /// when the characters around the position allow it.
+    pub fn is_assertion(&self) -> bool {
+        matches!(
+            self,
+            LineStart
+                | LineEnd
+                | MultiLineStart
+                | MultiLineEnd
+                | CrlfLineStart
+                | CrlfLineEnd
+                | WordBoundary { .. }
+                | NotWordBoundary { .. }
+        )
+    }
+
+    /// Returns true if the arrow is an assertion that holds between a `previous` and
+    /// a `next` character, `None` standing for the start and the end of the text.
+    pub fn holds(&self, previous: Option<CharKind>, next: Option<CharKind>) -> bool {
+        match self {
+            LineStart => previous.is_none(),
+            LineEnd => next.is_none(),
+            MultiLineStart => matches!(previous, None | Some(CharKind::LineBreak)),
+            MultiLineEnd => matches!(next, None | Some(CharKind::LineBreak)),
+            CrlfLineStart => match previous {
+                None | Some(CharKind::LineBreak) => true,
+                Some(CharKind::CarriageReturn) => next != Some(CharKind::LineBreak),
+                _ => false,
+            },
+            CrlfLineEnd => match next {
+                None | Some(CharKind::CarriageReturn) => true,
+                Some(CharKind::LineBreak) => previous != Some(CharKind::CarriageReturn),
+                _ => false,
+            },
+            WordBoundary { unicode } => is_word(previous, *unicode) != is_word(next, *unicode),
+            NotWordBoundary { unicode } => is_word(previous, *unicode) == is_word(next, *unicode),
+            _ => false,
+        }
+    }
+
+    /// Makes the arrow accept every case of its characters under Unicode simple case folding,
+    /// so that `k` also matches `K` and the Kelvin sign `\u{212A}`.
+    pub fn case_insensitive(self) -> Self {
+        match self {
+            Char(c) => match case_variants(c) {
+                [] => Char(c),
+                variants => OneOf(CharClass::from_chars(variants.iter().cloned().chain([c]))),
+            },
+            OneOf(class) => OneOf(case_insensitive_class(&class)),
+            NotOneOf(class) => NotOneOf(case_insensitive_class(&class)),
+            arrow => arrow,
+        }
+    }
+}
+
+/// Adds the other cases of the characters of the class. Only the characters that have
+/// other cases are looked at, so that large classes like `\p{L}` are cheap to fold.
+fn case_insensitive_class(class: &CharClass) -> CharClass {
+    let variants = CharClass::from_chars(
+        CASE_FOLDING
+            .iter()
+            .filter(|(c, _)| class.contains(*c))
+            .flat_map(|(_, variants)| variants.iter().cloned()),
+    );
+    class.union(&variants)
+}
+
+/// The other characters with the same simple case folding as `c`.
+fn case_variants(c: char) -> &'static [char] {
+    match CASE_FOLDING.binary_search_by_key(&c, |&(c, _)| c) {
+        Ok(i) => CASE_FOLDING[i].1,
+        Err(_) => &[],
+    }
+}
+
+/// Building blocks for the parser. Every fragment keeps two invariants that make
+/// `Graph::concat` and `Graph::attach_parallel` safe to use on them:
+/// nothing points back to the initial node and nothing leaves the final node.
+impl NFA {
+    pub fn single(arrow: NfaArrow) -> Self {
+        Graph::new(1).add_edge(0, arrow, 1)
+    }
+
+    pub fn optional(self) -> Self {
+        let final_node = self.final_node;
+        self.add_edge(0, Epsilon, final_node)
+    }
+
+    /// Same as `optional`, but skipping the fragment has a higher priority than going through it.
+    pub fn lazy_optional(self) -> Self {
+        let final_node = self.final_node;
+        self.prepend_edge(0, Epsilon, final_node)
+    }
+
+    /// Same as `add_edge`, but the edge comes before the other edges of `from`.
+    fn prepend_edge(mut self, from: usize, ch: NfaArrow, to: usize) -> Self {
+        self.edges
+            .entry(from)
+            .or_default()
+            .insert(0, Edge { ch, to });
+        self
+    }
+
+    pub fn plus(self) -> Self {
+        self.plus_with(true)
+    }
+
+    /// Same as `plus`, but leaving the loop has a higher priority than going through it again.
+    pub fn lazy_plus(self) -> Self {
+        self.plus_with(false)
+    }
+
+    fn plus_with(self, greedy: bool) -> Self {
+        let mut graph = Self::single(Epsilon).concat(self);
+        let last = graph.final_node;
+        // The final node is not always the highest one, like after `alternate`.
+        let fresh = graph.node_count();
+        graph = if greedy {
+            graph
+                .add_edge(last, Repeat, 1)
+                .add_edge(last, Epsilon, fresh)
+        } else {
+            graph
+                .add_edge(last, Epsilon, fresh)
+                .add_edge(last, Repeat, 1)
+        };
+        graph.final_node = fresh;
+        graph
+    }
+
+    /// Wraps the fragment in the arrows that record where the given capture group starts and ends.
+    pub fn group(self, index: usize) -> Self {
+        Self::single(Save(2 * index))
+            .concat(self)
+            .concat(Self::single(Save(2 * index + 1)))
+    }
+
+    pub fn star(self) -> Self {
+        self.plus().optional()
+    }
+
+    pub fn lazy_star(self) -> Self {
+        self.lazy_plus().lazy_optional()
+    }
+
+    /// Repeats the fragment from `min` to `max` times, or at least `min` times without a `max`.
+    /// Every repetition is a copy of the fragment. The optional copies follow each other
+    /// and the start of each one can skip to the end, so that `a{0,3}` works like
+    /// `(a(a(a)?)?)?` while being built in linear time. A lazy repetition prefers
+    /// the fewest repetitions, its skips come first like in `(a(a(a)??)??)??`.
+    pub fn repeat(self, min: usize, max: Option<usize>, greedy: bool) -> Self {
+        let mut graph = Graph::new(0);
+        for _ in 1..min {
+            graph = graph.concat(self.clone());
+        }
+
+        let optional = match max {
+            None if min == 0 && greedy => return self.star(),
+            None if min == 0 => return self.lazy_star(),
+            None if greedy => return graph.concat(self.plus()),
+            None => return graph.concat(self.lazy_plus()),
+            Some(0) => return graph,
+            Some(max) if min > 0 => {
+                graph = graph.concat(self.clone());
+                max - min
+            }
+            Some(max) => max,
+        };
+
+        let mut starts = Vec::with_capacity(optional);
+        for _ in 0..optional {
+            starts.push(graph.final_node);
+            graph = graph.concat(self.clone());
+        }
+        let end = graph.final_node;
+        for start in starts {
+            graph = if greedy {
+                graph.add_edge(start, Epsilon, end)
+            } else {
+                graph.prepend_edge(start, Epsilon, end)
+            };
+        }
+        graph
+    }
+
+    pub fn alternate(self, other: Self) -> Self {
+        let left = self.non_empty();
+        let final_node = left.final_node;
+        left.attach_parallel(other.non_empty(), 0, final_node)
+    }
+
+    /// Joins whole patterns into one NFA that accepts each of them in its own final node.
+    pub fn union(patterns: Vec<Self>) -> Self {
+        Graph::parallel(patterns.into_iter().map(Self::non_empty))
+    }
+
+    fn non_empty(self) -> Self {
+        if self.final_node == 0 {
+            Self::single(Epsilon)
+        } else {
+            self
+        }
+    }
+}
+
+/// States only follow the assertions once the characters around the position are known,
+/// right before consuming the next character or at the end of the text.
 pub fn initial_state(nfa: &NFA) -> NFAState {
     let mut state = HashSet::new();
     state.insert(0);
-    state.extend(step(&nfa, &state, |e| e.ch == LineStart));
-    follow_empty(&nfa, state)
+    follow_empty(nfa, state)
 }
 
-pub fn walk(nfa: NFA, text: String) -> bool {
-    let mut state = initial_state(&nfa);
+/// Returns the indices of the patterns whose accepting node is in the state.
+pub fn accepted(nfa: &NFA, state: &NFAState) -> Vec<usize> {
+    let final_nodes = nfa.final_nodes().iter().enumerate();
+    final_nodes
+        .filter(|(_, node)| state.contains(node))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Simulates the NFA directly, without building DFA states. Used as a reference in tests.
+#[cfg(test)]
+pub fn walk(nfa: &NFA, text: &str) -> bool {
+    let mut state = initial_state(nfa);
+    let mut previous = None;
 
-    for (i, c) in text.chars().enumerate() {
-        if state.contains(&nfa.final_node) {
+    for c in text.chars() {
+        let next = Some(CharKind::of(c));
+        let before = follow_assertions(nfa, state.clone(), previous, next);
+        if !accepted(nfa, &before).is_empty() {
             return true;
         }
 
-        state = follow_char(&nfa, &state, c, i == text.len() - 1);
+        state = follow_char(nfa, &state, previous, c, true);
+        previous = next;
 
         if state.is_empty() {
             return false;
         }
     }
 
-    state.contains(&nfa.final_node)
+    !accepted(nfa, &follow_assertions(nfa, state, previous, None)).is_empty()
 }
 
-pub fn follow_char(nfa: &NFA, state: &NFAState, c: char, is_last: bool) -> NFAState {
-    let mut new_state = step_with_char(&nfa, &state, c);
-    if is_last {
-        new_state.extend(step_with_line_end(&nfa, &new_state));
+/// Consumes `c`, `previous` being the kind of the character consumed before it.
+/// With `restart` the initial node is added back to the state,
+/// because an unanchored match may begin at any position.
+pub fn follow_char(
+    nfa: &NFA,
+    state: &NFAState,
+    previous: Option<CharKind>,
+    c: char,
+    restart: bool,
+) -> NFAState {
+    let state = follow_assertions(nfa, state.clone(), previous, Some(CharKind::of(c)));
+    let mut new_state = step_with_char(nfa, &state, c);
+    if restart {
+        new_state.insert(0);
     }
-    follow_empty(&nfa, new_state)
+    follow_empty(nfa, new_state)
+}
+
+/// Follows the assertions that hold between the `previous` and the `next` character,
+/// `None` standing for the start and the end of the text.
+pub fn follow_assertions(
+    nfa: &NFA,
+    state: NFAState,
+    previous: Option<CharKind>,
+    next: Option<CharKind>,
+) -> NFAState {
+    closure(nfa, state, |e| {
+        e.ch.is_epsilon() || e.ch.holds(previous, next)
+    })
+}
+
+/// Returns true if the state follows different edges depending on the characters around it.
+pub fn has_assertions(nfa: &NFA, state: &NFAState) -> bool {
+    state.iter().any(|node| {
+        nfa.edges
+            .get(node)
+            .is_some_and(|edges| edges.iter().any(|e| e.ch.is_assertion()))
+    })
 }
 
 fn step<F: Fn(&&Edge<NfaArrow>) -> bool>(nfa: &NFA, states: &NFAState, predicate: F) -> NFAState {
     let mut relevant_edges = HashSet::new();
 
     for s in states {
-        if let Some(edges) = nfa.edges.get(&s) {
+        if let Some(edges) = nfa.edges.get(s) {
             relevant_edges.extend(edges.iter().filter(&predicate).map(|e| e.to));
         }
     }
@@ -62,23 +405,21 @@ fn step<F: Fn(&&Edge<NfaArrow>) -> bool>(nfa: &NFA, states: &NFAState, predicate
     relevant_edges
 }
 
-fn step_with_line_end(nfa: &NFA, state: &NFAState) -> NFAState {
-    step(&nfa, &state, |e| e.ch == LineEnd)
+fn step_with_char(nfa: &NFA, state: &NFAState, c: char) -> NFAState {
+    step(nfa, state, |e| e.ch.accepts(c))
 }
 
-fn step_with_char(nfa: &NFA, state: &NFAState, c: char) -> NFAState {
-    step(nfa, state, |e| match &e.ch {
-        Char(ch) => c == *ch,
-        Dot => true,
-        OneOf(chars) => chars.contains(&c),
-        NotOneOf(chars) => !chars.contains(&c),
-        _ => false,
-    })
+fn follow_empty(nfa: &NFA, state: NFAState) -> NFAState {
+    closure(nfa, state, |e| e.ch.is_epsilon())
 }
 
-fn follow_empty(nfa: &NFA, mut state: NFAState) -> NFAState {
+fn closure<F: Fn(&&Edge<NfaArrow>) -> bool>(
+    nfa: &NFA,
+    mut state: NFAState,
+    predicate: F,
+) -> NFAState {
     loop {
-        let empty = step(&nfa, &state, |e| e.ch == Epsilon);
+        let empty = step(nfa, &state, &predicate);
         let diff: HashSet<_> = empty.difference(&state).collect();
         if diff.is_empty() {
             break;
@@ -92,15 +433,86 @@ fn follow_empty(nfa: &NFA, mut state: NFAState) -> NFAState {
 #[cfg(test)]
 mod nfa_test {
     use super::*;
+    use crate::error::RegexError;
     use crate::parser::parse;
     use crate::regex_tests;
 
-    fn check_for_pattern(pattern: &str, string: &str) -> Result<bool, String> {
-        let graph = parse(pattern)?;
-        Ok(walk(graph, string.to_string()))
+    fn check_for_pattern(pattern: &str, string: &str) -> Result<bool, RegexError> {
+        let graph = parse(pattern)?.nfa;
+        Ok(walk(&graph, string))
     }
 
     regex_tests!(check_for_pattern);
+
+    #[test]
+    fn test_holds() {
+        use CharKind::*;
+        let boundary = WordBoundary { unicode: true };
+        assert!(boundary.holds(None, Some(AsciiWord)));
+        assert!(boundary.holds(Some(Word), Some(Other)));
+        assert!(!boundary.holds(Some(AsciiWord), Some(Word)));
+        assert!(!boundary.holds(None, None));
+        let ascii = WordBoundary { unicode: false };
+        assert!(!ascii.holds(Some(Word), Some(Other)));
+        assert!(ascii.holds(Some(AsciiWord), Some(Word)));
+        assert!(NotWordBoundary { unicode: true }.holds(Some(LineBreak), Some(Other)));
+        assert!(LineStart.holds(None, Some(Other)) && !LineStart.holds(Some(LineBreak), None));
+        assert!(MultiLineStart.holds(Some(LineBreak), None));
+        assert!(MultiLineEnd.holds(Some(Other), Some(LineBreak)));
+        assert!(!LineEnd.holds(None, Some(LineBreak)));
+        assert!(!Epsilon.holds(None, None));
+        assert!(CrlfLineStart.holds(Some(CarriageReturn), Some(Other)));
+        assert!(!CrlfLineStart.holds(Some(CarriageReturn), Some(LineBreak)));
+        assert!(CrlfLineStart.holds(Some(LineBreak), Some(CarriageReturn)));
+        assert!(CrlfLineEnd.holds(Some(Other), Some(CarriageReturn)));
+        assert!(!CrlfLineEnd.holds(Some(CarriageReturn), Some(LineBreak)));
+        assert!(CrlfLineEnd.holds(Some(LineBreak), Some(LineBreak)));
+        assert!(!CrlfLineEnd.holds(Some(Other), Some(Other)));
+        assert_eq!(CharKind::of('\r'), CarriageReturn);
+        assert_eq!(CharKind::of('é'), Word);
+        assert_eq!(CharKind::of('_'), AsciiWord);
+        assert_eq!(CharKind::of('\n'), LineBreak);
+        assert_eq!(CharKind::of('-'), Other);
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let kelvin = '\u{212A}';
+        let k = OneOf(CharClass::from_chars(vec!['k', 'K', kelvin]));
+        assert_eq!(Char('k').case_insensitive(), k);
+        assert_eq!(Char(kelvin).case_insensitive(), k);
+        assert_eq!(Char('1').case_insensitive(), Char('1'));
+        assert_eq!(
+            OneOf(CharClass::new(vec![('a', 'b')])).case_insensitive(),
+            OneOf(CharClass::new(vec![('A', 'B'), ('a', 'b')]))
+        );
+        assert_eq!(
+            NotOneOf(CharClass::from_chars(vec!['K'])).case_insensitive(),
+            NotOneOf(CharClass::from_chars(vec!['k', 'K', kelvin]))
+        );
+        assert_eq!(AnyChar.case_insensitive(), AnyChar);
+    }
+
+    #[test]
+    fn test_repeat() {
+        let a = || NFA::single(Char('a'));
+        let greedy = Graph::new(3)
+            .add_edge(0, Char('a'), 1)
+            .add_edge(1, Char('a'), 2)
+            .add_edge(2, Char('a'), 3)
+            .add_edge(1, Epsilon, 3)
+            .add_edge(2, Epsilon, 3);
+        assert_eq!(a().repeat(1, Some(3), true), greedy);
+        let lazy = Graph::new(2)
+            .add_edge(0, Epsilon, 2)
+            .add_edge(0, Char('a'), 1)
+            .add_edge(1, Epsilon, 2)
+            .add_edge(1, Char('a'), 2);
+        assert_eq!(a().repeat(0, Some(2), false), lazy);
+        // Building the optional copies one after the other keeps this linear.
+        assert_eq!(a().repeat(0, Some(100_000), true).node_count(), 100_001);
+    }
+
     #[test]
     fn test_follow_empty() {
         let graph = Graph::new(3)
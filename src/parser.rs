@@ -1,190 +1,1304 @@
-use crate::graph::Graph;
-use crate::nfa::NfaArrow::*;
-use crate::nfa::{NfaArrow, NFA};
+use std::ops::Range;
 
-pub fn parse(pattern: &str) -> Result<NFA, String> {
-    Ok(parse_inner(pattern, None)?.0)
+use crate::ast::{Assertion, Ast, AstKind};
+use crate::class::CharClass;
+use crate::compiler;
+use crate::error::ErrorKind::*;
+use crate::error::{ErrorKind, RegexError};
+use crate::nfa::NFA;
+
+/// A parsed pattern: the NFA along with what is needed to report its capture groups.
+#[derive(Debug)]
+pub struct Parsed {
+    pub nfa: NFA,
+    /// Names of the capture groups indexed by group number.
+    /// Group 0 is the whole match, it and the unnamed groups have no name.
+    pub groups: Vec<Option<String>>,
+}
+
+/// The largest count accepted by default in a counted repetition like `a{3}`.
+pub const DEFAULT_REPETITION_LIMIT: usize = 1000;
+
+/// The deepest nesting of groups and character classes accepted by default.
+pub const DEFAULT_NESTING_LIMIT: usize = 250;
+
+/// Options that change the meaning of parts of the pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flags {
+    /// Letters match both their upper and lower case.
+    pub case_insensitive: bool,
+    /// `^` and `$` match at the start and the end of every line.
+    pub multi_line: bool,
+    /// `.` matches `\n` too.
+    pub dot_matches_new_line: bool,
+    /// `\r\n` ends lines as well as `\n`: in multi-line mode `^` and `$` match around `\r`
+    /// but not between `\r` and `\n`, and `.` does not match `\r`.
+    pub crlf: bool,
+    /// Whitespace is ignored and `#` starts a comment up to the end of the line,
+    /// except in character classes and when escaped.
+    pub verbose: bool,
+    /// `\d`, `\w` and `\s` match Unicode characters rather than only ASCII ones.
+    pub unicode: bool,
+    /// The largest count accepted in a counted repetition.
+    pub repetition_limit: usize,
+    /// The deepest nesting of groups and character classes, which bounds the recursion
+    /// of the parser and of the compiler.
+    pub nesting_limit: usize,
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        Self {
+            case_insensitive: false,
+            multi_line: false,
+            dot_matches_new_line: false,
+            crlf: false,
+            verbose: false,
+            unicode: true,
+            repetition_limit: DEFAULT_REPETITION_LIMIT,
+            nesting_limit: DEFAULT_NESTING_LIMIT,
+        }
+    }
+}
+
+#[cfg(test)]
+pub fn parse(pattern: &str) -> Result<Parsed, RegexError> {
+    parse_with_flags(pattern, Flags::default())
+}
+
+#[cfg(test)]
+pub fn parse_with_flags(pattern: &str, flags: Flags) -> Result<Parsed, RegexError> {
+    parse_with_limit(pattern, flags, usize::MAX)
+}
+
+/// Parses the pattern and compiles it, failing with `SizeLimitExceeded`
+/// if the NFA would have more than `size_limit` nodes.
+pub fn parse_with_limit(
+    pattern: &str,
+    flags: Flags,
+    size_limit: usize,
+) -> Result<Parsed, RegexError> {
+    let (ast, groups) = parse_ast(pattern, flags)?;
+    let nfa = compiler::compile(&ast, size_limit, flags.nesting_limit)
+        .map_err(|e| e.with_pattern(pattern))?;
+    Ok(Parsed { nfa, groups })
 }
 
-fn parse_inner(pattern: &str, stop_at: Option<char>) -> Result<(NFA, usize), String> {
-    let mut i = 0;
-    let mut graph = Graph::new(0);
-    if stop_at == None {
-        graph = graph.add_edge(0, Dot, 0);
+/// Parses the pattern into its syntax tree, along with the names of its capture groups
+/// indexed by group number.
+pub fn parse_ast(pattern: &str, flags: Flags) -> Result<(Ast, Vec<Option<String>>), RegexError> {
+    let mut parser = Parser::new(pattern);
+    let ast = parser
+        .parse_alternation(flags, false)
+        .map_err(|e| e.with_pattern(pattern))?;
+    Ok((ast, parser.groups))
+}
+
+fn error(kind: ErrorKind, span: Range<usize>) -> RegexError {
+    RegexError::new(kind, span)
+}
+
+/// Reads a pattern from left to right. It keeps the byte offset of the next character,
+/// so that every span is known without counting characters from the start again.
+struct Parser<'p> {
+    pattern: &'p str,
+    pos: usize,
+    /// Names of the capture groups opened so far, groups are numbered
+    /// by the position of their opening parenthesis starting from 1.
+    groups: Vec<Option<String>>,
+    /// The number of groups and classes that the current position is in.
+    depth: usize,
+}
+
+impl<'p> Parser<'p> {
+    fn new(pattern: &'p str) -> Self {
+        Self {
+            pattern,
+            pos: 0,
+            groups: vec![None],
+            depth: 0,
+        }
     }
-    let mut previous_node = 0;
 
-    loop {
-        let mut step = 1;
-        let final_node = graph.final_node;
+    fn rest(&self) -> &'p str {
+        &self.pattern[self.pos..]
+    }
 
-        match pattern.chars().nth(i) {
-            None => break,
-            Some(c) if stop_at == Some(c) => {
-                i += step;
-                return Ok((graph, i));
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.eat_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    fn eat_str(&mut self, prefix: &str) -> bool {
+        let found = self.rest().starts_with(prefix);
+        if found {
+            self.pos += prefix.len();
+        }
+        found
+    }
+
+    /// Byte offset after the next `count` characters, or the length of the pattern.
+    fn offset_after(&self, count: usize) -> usize {
+        self.pos
+            + self
+                .rest()
+                .chars()
+                .take(count)
+                .map(char::len_utf8)
+                .sum::<usize>()
+    }
+
+    fn skip(&mut self, count: usize) {
+        self.pos = self.offset_after(count);
+    }
+
+    /// Enters the group or class opened at `open`, unless that nests them deeper than
+    /// the limit. Every group and class is parsed by a recursive call, the limit keeps
+    /// patterns like a long run of `(` from overflowing the stack.
+    fn enter(&mut self, open: usize, limit: usize) -> Result<(), RegexError> {
+        if self.depth >= limit {
+            return Err(error(NestingTooDeep, open..open + 1));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Parses alternatives up to the end of the pattern, or up to and including the `)`
+    /// closing the group when `in_group`. `flags` are the ones in effect where the
+    /// alternatives start, inline flags like `(?i)` change them up to the end of the group.
+    fn parse_alternation(&mut self, mut flags: Flags, in_group: bool) -> Result<Ast, RegexError> {
+        let start = self.pos;
+        let mut alternatives = vec![self.parse_concat(&mut flags)?];
+        let end = loop {
+            let end = self.pos;
+            match self.bump() {
+                Some('|') => alternatives.push(self.parse_concat(&mut flags)?),
+                Some(')') if in_group => break end,
+                Some(_) => return Err(error(UnopenedGroup, end..self.pos)),
+                None if in_group => return Err(error(UnclosedGroup, end..end)),
+                None => break end,
             }
-            Some('.') => {
-                graph = graph.add_edge(final_node, Dot, final_node + 1);
-                graph.final_node += 1;
-                previous_node = final_node;
+        };
+
+        Ok(if alternatives.len() == 1 {
+            alternatives.remove(0)
+        } else {
+            Ast::new(AstKind::Alternation(alternatives), start..end)
+        })
+    }
+
+    /// Parses atoms and their quantifiers up to `|`, `)` or the end of the pattern.
+    fn parse_concat(&mut self, flags: &mut Flags) -> Result<Ast, RegexError> {
+        let start = self.pos;
+        let mut items: Vec<Ast> = Vec::new();
+        // Whether the last item is an atom that a quantifier can apply to.
+        let mut quantifiable = false;
+
+        while let Some(c) = self.peek() {
+            match c {
+                '|' | ')' => break,
+                // The atom stays quantifiable, a quantifier after the whitespace applies to it.
+                c if flags.verbose && c.is_whitespace() => {
+                    self.bump();
+                }
+                '#' if flags.verbose => {
+                    self.pos = self
+                        .rest()
+                        .find('\n')
+                        .map_or(self.pattern.len(), |end| self.pos + end);
+                }
+                '?' | '*' | '+' | '{' => {
+                    let (min, max) = self.parse_quantifier(quantifiable, flags.repetition_limit)?;
+                    // A `?` right after a quantifier makes it lazy: it matches as few times
+                    // as possible.
+                    let greedy = !self.eat('?');
+                    let inner = items.pop().expect("a quantifier follows an atom");
+                    let span = inner.span.start..self.pos;
+                    let kind = AstKind::Repetition {
+                        inner: Box::new(inner),
+                        min,
+                        max,
+                        greedy,
+                    };
+                    items.push(Ast::new(kind, span));
+                    quantifiable = false;
+                }
+                '(' => match self.parse_group(flags)? {
+                    Some(group) => {
+                        items.push(group);
+                        quantifiable = true;
+                    }
+                    None => quantifiable = false,
+                },
+                c => {
+                    items.push(self.parse_atom(c, *flags)?);
+                    quantifiable = true;
+                }
             }
-            Some('^') => {
-                graph = graph.add_edge(final_node, LineStart, final_node + 1);
-                graph.final_node += 1;
-                previous_node = final_node;
+        }
+
+        let span = start..self.pos;
+        Ok(match items.len() {
+            0 => Ast::new(AstKind::Empty, span),
+            1 => items.remove(0),
+            _ => Ast::new(AstKind::Concat(items), span),
+        })
+    }
+
+    /// Parses `?`, `*`, `+` or a counted repetition like `{2,5}` into its bounds.
+    fn parse_quantifier(
+        &mut self,
+        quantifiable: bool,
+        limit: usize,
+    ) -> Result<(usize, Option<usize>), RegexError> {
+        let start = self.pos;
+        let bounds = match self.bump() {
+            Some('?') => (0, Some(1)),
+            Some('+') => (1, None),
+            Some('*') => (0, None),
+            _ => {
+                let rest = self.rest();
+                let end = rest.find('}').map_or(rest.len(), |end| end + 1);
+                let braces = start..self.pos + end;
+                if !quantifiable {
+                    return Err(error(DanglingQuantifier, braces));
+                }
+                let (min, max, len) =
+                    parse_repetition(rest, limit).map_err(|kind| error(kind, braces))?;
+                self.skip(len);
+                return Ok((min, max));
+            }
+        };
+        if !quantifiable {
+            return Err(error(DanglingQuantifier, start..self.pos));
+        }
+        Ok(bounds)
+    }
+
+    /// Parses a group from its `(`, or returns `None` for inline flags like `(?i)`
+    /// after applying them to `flags`.
+    fn parse_group(&mut self, flags: &mut Flags) -> Result<Option<Ast>, RegexError> {
+        let open = self.pos;
+        self.bump();
+        let (index, group_flags) = match self.parse_group_kind(open, *flags)? {
+            GroupKind::Capture(name) => {
+                self.groups.push(name);
+                (Some(self.groups.len() - 1), *flags)
             }
-            Some('$') => {
-                graph = graph.add_edge(final_node, LineEnd, final_node + 1);
-                graph.final_node += 1;
-                previous_node = final_node;
+            GroupKind::NonCapture(group_flags) => (None, group_flags),
+            GroupKind::SetFlags(new_flags) => {
+                *flags = new_flags;
+                return Ok(None);
             }
-            Some('\\') => match pattern.chars().nth(i + 1) {
-                None => return Err("escape character at EOL".to_string()),
-                Some(c) if ['\\', '+', '*', '(', ')', '[', ']', '.', '?'].contains(&c) => {
-                    graph = graph.add_edge(final_node, Char(c), final_node + 1);
-                    graph.final_node += 1;
-                    previous_node = final_node;
-                    step += 1;
-                }
-                Some(c) => return Err(format!("Unexpected character escaped: {}", c)),
+        };
+
+        self.enter(open, flags.nesting_limit)?;
+        let inner = self
+            .parse_alternation(group_flags, true)
+            .map_err(|e| match e.kind() {
+                // Point at the parenthesis that is not closed rather than at the end.
+                UnclosedGroup => e.with_span(open..open + 1),
+                _ => e,
+            })?;
+        self.leave();
+        let kind = AstKind::Group {
+            index,
+            inner: Box::new(inner),
+        };
+        Ok(Some(Ast::new(kind, open..self.pos)))
+    }
+
+    /// Parses what follows the opening parenthesis at `open`: `?:` or `?flags:`
+    /// for a non-capturing group, `?flags)` for inline flags, `?P<name>` or `?<name>`
+    /// for a named group and nothing for a numbered one.
+    fn parse_group_kind(&mut self, open: usize, flags: Flags) -> Result<GroupKind, RegexError> {
+        if self.eat_str("?:") {
+            return Ok(GroupKind::NonCapture(flags));
+        } else if !self.eat_str("?P<") && !self.eat_str("?<") {
+            return match self.peek() {
+                Some('?') => self.parse_flags(open, flags),
+                _ => Ok(GroupKind::Capture(None)),
+            };
+        }
+
+        let start = self.pos;
+        self.pos = self
+            .rest()
+            .find('>')
+            .map_or(self.pattern.len(), |end| self.pos + end);
+        let name = &self.pattern[start..self.pos];
+        if !self.eat('>') {
+            return Err(error(UnclosedGroupName, start..self.pos));
+        }
+        let span = start..self.pos - 1;
+        if !is_valid_group_name(name) {
+            return Err(error(InvalidGroupName, span));
+        }
+        if self.groups.iter().flatten().any(|group| group == name) {
+            return Err(error(DuplicateGroupName, span));
+        }
+
+        Ok(GroupKind::Capture(Some(name.to_string())))
+    }
+
+    /// Parses inline flags like `?i)`, `?-s)` or `?im-x:`, that enable the flags before `-`
+    /// and disable the ones after it. Flags are `i` for case-insensitive, `m` for multi-line,
+    /// `s` for dot-matches-new-line, `R` for CRLF line endings and `x` for verbose.
+    fn parse_flags(&mut self, open: usize, mut flags: Flags) -> Result<GroupKind, RegexError> {
+        self.bump();
+        let first = self.pos;
+        let mut enable = true;
+        let mut expects_flag = true;
+
+        while let Some(c) = self.peek() {
+            let at = self.pos;
+            self.bump();
+            let flag = match c {
+                'i' => &mut flags.case_insensitive,
+                'm' => &mut flags.multi_line,
+                's' => &mut flags.dot_matches_new_line,
+                'R' => &mut flags.crlf,
+                'x' => &mut flags.verbose,
+                '-' if enable && (at == first || !expects_flag) => {
+                    enable = false;
+                    expects_flag = true;
+                    continue;
+                }
+                ':' | ')' if expects_flag => return Err(error(MissingGroupFlag, at..self.pos)),
+                ':' => return Ok(GroupKind::NonCapture(flags)),
+                ')' => return Ok(GroupKind::SetFlags(flags)),
+                _ => return Err(error(UnknownGroupFlag, at..self.pos)),
+            };
+            *flag = enable;
+            expects_flag = false;
+        }
+
+        Err(error(UnclosedGroup, open..open + 1))
+    }
+
+    /// Parses the character `c` at the current position, and what follows it
+    /// if it starts an escape sequence or a class.
+    fn parse_atom(&mut self, c: char, flags: Flags) -> Result<Ast, RegexError> {
+        let start = self.pos;
+        self.bump();
+        let kind = match c {
+            '.' => AstKind::Dot {
+                matches_new_line: flags.dot_matches_new_line,
+                crlf: flags.crlf,
             },
-            Some('|') => {
-                let right = parse_inner(&pattern[i + 1..], stop_at)?;
-                if right.0.edges.is_empty() {
-                    graph = graph.add_edge(0, Epsilon, final_node)
+            '^' if flags.multi_line && flags.crlf => AstKind::Assertion(Assertion::CrlfLineStart),
+            '^' if flags.multi_line => AstKind::Assertion(Assertion::MultiLineStart),
+            '^' => AstKind::Assertion(Assertion::LineStart),
+            '$' if flags.multi_line && flags.crlf => AstKind::Assertion(Assertion::CrlfLineEnd),
+            '$' if flags.multi_line => AstKind::Assertion(Assertion::MultiLineEnd),
+            '$' => AstKind::Assertion(Assertion::LineEnd),
+            '\\' => self.parse_escape_atom(start, flags)?,
+            '[' => {
+                let (class, negated) = self.parse_class_set(start, flags)?;
+                // A negated class is only empty when it negates every character.
+                let matches_nothing = if negated {
+                    class.negate().is_empty()
                 } else {
-                    graph = graph.attach_parallel(right.0, 0, final_node);
+                    class.is_empty()
+                };
+                if matches_nothing {
+                    return Err(error(EmptyClass, start..start + 1));
                 }
-                return Ok((graph, i + step + right.1));
-            }
-            Some('?') => {
-                if i == 0 || !can_apply_metacharacter(pattern.chars().nth(i - 1)) {
-                    return Err("Can not apply '?'".to_string());
+                AstKind::Class {
+                    class,
+                    negated,
+                    case_insensitive: flags.case_insensitive,
                 }
-                graph = graph.add_edge(previous_node, Epsilon, final_node);
             }
-            Some('+') => {
-                if i == 0 || !can_apply_metacharacter(pattern.chars().nth(i - 1)) {
-                    return Err("Can not apply '+'".to_string());
-                }
-                graph = graph.add_edge(final_node, Epsilon, previous_node);
+            c => AstKind::Literal {
+                c,
+                case_insensitive: flags.case_insensitive,
+            },
+        };
+        Ok(Ast::new(kind, start..self.pos))
+    }
+
+    /// Parses what follows the `\` at `start` outside of a class.
+    fn parse_escape_atom(&mut self, start: usize, flags: Flags) -> Result<AstKind, RegexError> {
+        let kind = match self.peek() {
+            None => return Err(error(TrailingEscape, start..self.pos)),
+            Some(c @ ('p' | 'P')) => {
+                self.bump();
+                let (class, len) = parse_property(self.rest().chars())
+                    .map_err(|(kind, len)| error(kind, start..self.offset_after(len)))?;
+                self.skip(len);
+                return Ok(AstKind::Class {
+                    class,
+                    negated: c == 'P',
+                    case_insensitive: flags.case_insensitive,
+                });
             }
-            Some('*') => {
-                if i == 0 || !can_apply_metacharacter(pattern.chars().nth(i - 1)) {
-                    return Err("Can not apply '*'".to_string());
+            Some('A') => AstKind::Assertion(Assertion::LineStart),
+            Some('z') => AstKind::Assertion(Assertion::LineEnd),
+            Some('b') => AstKind::Assertion(Assertion::WordBoundary {
+                unicode: flags.unicode,
+            }),
+            Some('B') => AstKind::Assertion(Assertion::NotWordBoundary {
+                unicode: flags.unicode,
+            }),
+            Some(c) => match perl_class(c, flags.unicode) {
+                Some((class, negated)) => AstKind::Class {
+                    class,
+                    negated,
+                    case_insensitive: false,
+                },
+                None => {
+                    let (c, len) = parse_escape(self.rest().chars())
+                        .map_err(|(kind, len)| error(kind, start..self.offset_after(len)))?;
+                    self.skip(len);
+                    return Ok(AstKind::Literal {
+                        c,
+                        case_insensitive: flags.case_insensitive,
+                    });
                 }
-                graph = graph.add_edge(previous_node, Epsilon, final_node);
-                graph = graph.add_edge(final_node, Epsilon, previous_node);
+            },
+        };
+        self.bump();
+        Ok(kind)
+    }
+
+    /// Parses what follows the `[` at `open` up to the closing `]`, where verbose mode
+    /// does not apply. Returns the class without its negation and whether it is negated.
+    /// `]` right after the opening bracket and `-` at either end of the class are taken
+    /// literally. POSIX classes like `[:alpha:]` or `[:^alpha:]` for the negated form
+    /// can be mixed with the other items.
+    ///
+    /// Classes can be nested and combined with `&&` for the intersection, `--` for the
+    /// difference and `~~` for the symmetric difference, like `[\p{L}--[a-z]]`. Items next
+    /// to each other are joined first, then the set operations apply from left to right,
+    /// so `[a-z&&[^aeiou]--x]` is `[[[a-z]&&[^aeiou]]--[x]]`.
+    fn parse_class_set(
+        &mut self,
+        open: usize,
+        flags: Flags,
+    ) -> Result<(CharClass, bool), RegexError> {
+        self.enter(open, flags.nesting_limit)?;
+        let negated = self.eat('^');
+        let first = self.pos;
+        let mut ranges = Vec::new();
+        // The class on the left of the last operator, with that operator.
+        let mut left: Option<(CharClass, char)> = None;
+        let mut operand_start = first;
+
+        loop {
+            let at = self.pos;
+            match (self.peek(), self.peek_nth(1)) {
+                (None, _) => return Err(error(UnclosedClass, open..open + 1)),
+                (Some(']'), _) if at > first => break,
+                (Some(op @ ('&' | '-' | '~')), Some(next)) if next == op => {
+                    if at == operand_start {
+                        return Err(error(MissingClassOperand, at..at + 2));
+                    }
+                    let right = CharClass::new(ranges.drain(..));
+                    left = Some((apply_class_operation(left, right), op));
+                    self.skip(2);
+                    operand_start = self.pos;
+                    continue;
+                }
+                _ => (),
             }
-            Some('(') => {
-                let inner = parse_inner(&pattern[i + 1..], Some(')'))?;
-                step += inner.1;
-                graph = graph.concat(inner.0);
-                previous_node = final_node;
+
+            let item = self.parse_class_item(open, flags)?;
+            // `--` is the difference unless it ends the class, like in `[!--]`.
+            let is_range = self.peek() == Some('-')
+                && match self.peek_nth(1) {
+                    None | Some(']') => false,
+                    Some('-') => self.peek_nth(2) == Some(']'),
+                    Some(_) => true,
+                };
+            if is_range {
+                self.bump();
+                let end = self.parse_class_item(open, flags)?;
+                match (item, end) {
+                    (ClassItem::Char(start), ClassItem::Char(end)) if start <= end => {
+                        ranges.push((start, end))
+                    }
+                    _ => return Err(error(InvalidClassRange, at..self.pos)),
+                }
+            } else {
+                match item {
+                    ClassItem::Char(c) => ranges.push((c, c)),
+                    ClassItem::Class(class) => ranges.extend_from_slice(class.ranges()),
+                }
             }
+        }
+
+        if left.is_some() && self.pos == operand_start {
+            return Err(error(MissingClassOperand, self.pos - 2..self.pos));
+        }
+        self.bump();
+        self.leave();
+        Ok((apply_class_operation(left, CharClass::new(ranges)), negated))
+    }
+
+    /// Parses the possibly escaped item at the current position in the class
+    /// opened at `open`.
+    fn parse_class_item(&mut self, open: usize, flags: Flags) -> Result<ClassItem, RegexError> {
+        let start = self.pos;
+        match self.bump() {
+            Some('\\') => match self.peek() {
+                Some(c @ ('p' | 'P')) => {
+                    self.bump();
+                    let (class, len) = parse_property(self.rest().chars())
+                        .map_err(|(kind, len)| error(kind, start..self.offset_after(len)))?;
+                    self.skip(len);
+                    Ok(ClassItem::Class(if c == 'P' {
+                        class.negate()
+                    } else {
+                        class
+                    }))
+                }
+                Some(c) => match perl_class(c, flags.unicode) {
+                    Some((class, negated)) => {
+                        self.bump();
+                        Ok(ClassItem::Class(if negated {
+                            class.negate()
+                        } else {
+                            class
+                        }))
+                    }
+                    None => {
+                        let (c, len) = parse_escape(self.rest().chars())
+                            .map_err(|(kind, len)| error(kind, start..self.offset_after(len)))?;
+                        self.skip(len);
+                        Ok(ClassItem::Char(c))
+                    }
+                },
+                None => Err(error(UnclosedClass, open..open + 1)),
+            },
             Some('[') => {
-                let (char_class, len) = parse_character_class(&pattern[i + 1..])?;
-                graph = graph.add_edge(final_node, char_class, final_node + 1);
-                step += len;
-                graph.final_node += 1;
-                previous_node = final_node;
-            }
-            Some(')') => return Err("unexpected character: )".to_string()),
-            Some(c) => {
-                graph = graph.add_edge(final_node, Char(c), final_node + 1);
-                graph.final_node += 1;
-                previous_node = final_node;
+                if self.peek() == Some(':') {
+                    match posix_class(&self.rest()[1..]) {
+                        Some(Ok((class, len))) => {
+                            self.skip(1 + len);
+                            return Ok(ClassItem::Class(class));
+                        }
+                        Some(Err(len)) => {
+                            return Err(error(UnknownPosixClass, start..self.offset_after(1 + len)))
+                        }
+                        None => (),
+                    }
+                }
+                // Without the surrounding colons this is a nested class.
+                let (class, negated) = self.parse_class_set(start, flags)?;
+                Ok(ClassItem::Class(if negated {
+                    class.negate()
+                } else {
+                    class
+                }))
             }
+            Some(c) => Ok(ClassItem::Char(c)),
+            None => Err(error(UnclosedClass, open..open + 1)),
         }
+    }
+}
 
-        i += step;
+enum GroupKind {
+    Capture(Option<String>),
+    /// A non-capturing group with the flags that apply inside of it.
+    NonCapture(Flags),
+    /// Not a group but flags like `(?i)` that apply up to the end of the enclosing group.
+    SetFlags(Flags),
+}
+
+pub fn is_valid_group_name(name: &str) -> bool {
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
     }
+}
+
+/// Parses what follows `{` in a counted repetition: `n}`, `n,}` or `n,m}`.
+/// Returns the bounds and the length of the repetition after the brace.
+/// Errors are reported without a span, the caller knows where the repetition is.
+fn parse_repetition(
+    repetition: &str,
+    limit: usize,
+) -> Result<(usize, Option<usize>, usize), ErrorKind> {
+    let bounds = match repetition.find('}') {
+        None => return Err(UnclosedRepetition),
+        Some(end) => &repetition[..end],
+    };
+    let count = |n: &str| {
+        if n.is_empty() || !n.chars().all(|c| c.is_ascii_digit()) {
+            return Err(InvalidRepetition);
+        }
+        match n.parse() {
+            Ok(n) if n <= limit => Ok(n),
+            _ => Err(RepetitionTooLarge),
+        }
+    };
 
-    match stop_at {
-        None => Ok((graph, i)),
-        Some(c) => Err(format!("Expected {} got end of line", c)),
+    let (min, max) = match bounds.split_once(',') {
+        None => {
+            let n = count(bounds)?;
+            (n, Some(n))
+        }
+        Some((min, "")) => (count(min)?, None),
+        Some((min, max)) => (count(min)?, Some(count(max)?)),
+    };
+    if max.is_some_and(|max| max < min) {
+        return Err(InvalidRepetitionRange);
     }
+    Ok((min, max, bounds.chars().count() + 1))
 }
 
-fn parse_character_class(char_class: &str) -> Result<(NfaArrow, usize), String> {
-    let mut j = 0;
-    let mut chars = Vec::new();
-    let mut exclusive = false;
+/// The class of a Perl shorthand like `\d` and whether it is negated, as the upper case ones are.
+fn perl_class(c: char, unicode: bool) -> Option<(CharClass, bool)> {
+    let class = CharClass::perl(c.to_ascii_lowercase(), unicode)?;
+    Some((class, c.is_ascii_uppercase()))
+}
+
+/// Parses an escape sequence that stands for a single character, from the character after `\`:
+/// an escaped punctuation character like `\$`, a control character like `\n`, or a code point
+/// like `\x7F`, `\x{1F600}` or `\u{1F600}`. Returns the character and the number of characters
+/// read. Errors come with the number of characters to report, the caller knows where they start.
+fn parse_escape<I: Iterator<Item = char>>(
+    mut chars: I,
+) -> Result<(char, usize), (ErrorKind, usize)> {
+    let escaped = match chars.next() {
+        None => return Err((TrailingEscape, 0)),
+        Some('n') => '\n',
+        Some('t') => '\t',
+        Some('r') => '\r',
+        Some('f') => '\u{C}',
+        Some('v') => '\u{B}',
+        Some('0') => '\0',
+        Some(' ') => ' ',
+        Some(c @ ('x' | 'u')) => return parse_code_point(c, chars),
+        Some(c) if c.is_ascii_punctuation() => c,
+        Some(_) => return Err((BadEscape, 1)),
+    };
+    Ok((escaped, 1))
+}
+
+/// Parses the hexadecimal code point after `\x` or `\u`: exactly two digits after `\x`,
+/// or between one and six digits in braces after both of them.
+fn parse_code_point<I: Iterator<Item = char>>(
+    escape: char,
+    mut chars: I,
+) -> Result<(char, usize), (ErrorKind, usize)> {
+    let mut digits = String::new();
+    let len = match chars.next() {
+        Some('{') => {
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) if c.is_ascii_hexdigit() => digits.push(c),
+                    Some(_) => return Err((InvalidHexEscape, digits.len() + 3)),
+                    None => return Err((UnclosedHexEscape, digits.len() + 2)),
+                }
+            }
+            if digits.is_empty() || digits.len() > 6 {
+                return Err((InvalidHexEscape, digits.len() + 3));
+            }
+            digits.len() + 3
+        }
+        Some(c) if escape == 'x' => {
+            digits.push(c);
+            digits.extend(chars.next());
+            if digits.len() < 2 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err((InvalidHexEscape, 1 + digits.chars().count()));
+            }
+            3
+        }
+        _ => return Err((InvalidHexEscape, 1)),
+    };
+
+    match u32::from_str_radix(&digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+    {
+        Some(c) => Ok((c, len)),
+        None => Err((InvalidCodePoint, len)),
+    }
+}
 
-    loop {
-        match char_class.chars().nth(j) {
-            None => return Err("Unexpected EOL".to_string()),
-            Some('\\') => {
-                let c = char_class
-                    .chars()
-                    .nth(j + 1)
-                    .ok_or_else(|| "Unexpected EOL".to_string())?;
-                chars.push(c);
-                j += 1;
+/// Parses the name of a Unicode class after `\p` or `\P`: a single letter like in `\pL`
+/// or a name in braces like in `\p{Greek}`. Returns the class and the number of characters
+/// read. Errors come with the number of characters to report, the caller knows where they start.
+fn parse_property<I: Iterator<Item = char>>(
+    mut chars: I,
+) -> Result<(CharClass, usize), (ErrorKind, usize)> {
+    let (name, len) = match chars.next() {
+        Some('{') => {
+            let mut name = String::new();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => return Err((UnclosedProperty, 1 + name.chars().count())),
+                }
             }
-            Some('^') if j == 0 => exclusive = true,
-            Some(']') if j == 0 || (exclusive && j == 1) => chars.push(']'),
-            Some(']') => break,
-            Some(option) => chars.push(option),
+            let len = name.chars().count() + 2;
+            (name, len)
         }
-        j += 1;
+        Some(c) => (c.to_string(), 1),
+        None => return Err((UnclosedProperty, 0)),
+    };
+
+    match CharClass::property(&name) {
+        Some(class) => Ok((class, len)),
+        None => Err((UnknownProperty, len)),
     }
+}
 
-    if chars.is_empty() {
-        return Err("Empty character class".to_string());
+fn apply_class_operation(left: Option<(CharClass, char)>, right: CharClass) -> CharClass {
+    match left {
+        None => right,
+        Some((left, '&')) => left.intersection(&right),
+        Some((left, '-')) => left.difference(&right),
+        Some((left, _)) => left.symmetric_difference(&right),
     }
-    Ok((
-        if exclusive {
-            NotOneOf(chars)
-        } else {
-            OneOf(chars)
-        },
-        j + 1,
-    ))
 }
 
-fn can_apply_metacharacter(ch: Option<char>) -> bool {
-    match ch {
-        None | Some('*') | Some('+') | Some('?') => false,
-        _ => true,
+enum ClassItem {
+    Char(char),
+    /// A Perl shorthand class like `\d`, a Unicode class like `\p{Greek}`,
+    /// a POSIX class like `[:alpha:]` or a nested class like `[a-z]`.
+    Class(CharClass),
+}
+
+/// Parses what follows `[:` in a POSIX class up to the closing `:]`.
+/// Returns the class and the number of characters read, `None` if the next `]` does not
+/// follow a colon or if the name has a `[`, and the number of characters read
+/// as an error if the name is unknown.
+fn posix_class(rest: &str) -> Option<Result<(CharClass, usize), usize>> {
+    let name = rest[..rest.find(']')?].strip_suffix(':')?;
+    if name.contains('[') {
+        return None;
     }
+    let len = name.chars().count() + 2;
+    let (name, negated) = match name.strip_prefix('^') {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+
+    Some(match CharClass::posix(name) {
+        Some(class) if negated => Ok((class.negate(), len)),
+        Some(class) => Ok((class, len)),
+        None => Err(len),
+    })
 }
 
 #[cfg(test)]
 mod nfa_test {
     use super::*;
+    use crate::graph::Graph;
+    use crate::nfa::NfaArrow::{self, *};
 
     #[test]
     fn test_parse_inner() {
-        let graph = Graph::new(3)
-            .add_edge(0, Dot, 0)
-            .add_edge(1, OneOf(vec!['b', 'c', 'd']), 2)
-            .add_edge(2, Epsilon, 1)
-            .add_edge(2, Char('e'), 3)
-            .add_edge(5, Char('\\'), 3)
-            .add_edge(4, Epsilon, 0)
-            .add_edge(4, Dot, 5)
+        let graph = Graph::new(5)
             .add_edge(0, Char('a'), 1)
-            .add_edge(0, Char('q'), 4)
-            .add_edge(0, Epsilon, 4)
-            .add_edge(0, Epsilon, 3);
+            .add_edge(0, Save(2), 6)
+            .add_edge(0, Epsilon, 5)
+            .add_edge(1, Epsilon, 2)
+            .add_edge(2, OneOf(CharClass::new(vec![('b', 'd')])), 3)
+            .add_edge(3, Repeat, 2)
+            .add_edge(3, Epsilon, 4)
+            .add_edge(4, Char('e'), 5)
+            .add_edge(6, Epsilon, 7)
+            .add_edge(6, Epsilon, 9)
+            .add_edge(7, Char('q'), 8)
+            .add_edge(8, Repeat, 7)
+            .add_edge(8, Epsilon, 9)
+            .add_edge(9, Dot, 10)
+            .add_edge(10, Char('\\'), 11)
+            .add_edge(11, Save(3), 5);
 
-        match parse_inner(r"a[bcd]+e|(q*.\\)?", None) {
+        match parse(r"a[bcd]+e|(q*.\\)?") {
             Err(e) => panic!("Failed to parse: {}", e),
-            Ok(res) => assert_eq!(res, (graph, 17)),
+            Ok(res) => assert_eq!(res.nfa, graph),
         }
     }
 
     #[test]
     fn test_parse_brackets() {
-        let graph = Graph::new(1)
-            .add_edge(0, Dot, 0)
-            .add_edge(0, OneOf(vec!['b', 'c']), 1);
+        let graph = Graph::new(1).add_edge(0, OneOf(CharClass::from_chars(vec!['b', 'c'])), 1);
+
+        match parse(r"[bc]") {
+            Err(e) => panic!("Failed to parse: {}", e),
+            Ok(res) => assert_eq!(res.nfa, graph),
+        }
+    }
+
+    /// The class parsed from `[` followed by `pattern`, and the length of the class.
+    fn class_with(pattern: &str, flags: Flags) -> (NfaArrow, usize) {
+        let pattern = format!("[{}", pattern);
+        let mut parser = Parser::new(&pattern);
+        parser.bump();
+        match parser.parse_class_set(0, flags) {
+            Err(e) => panic!("Failed to parse {}: {}", pattern, e),
+            Ok((class, true)) => (NotOneOf(class), parser.pos),
+            Ok((class, false)) => (OneOf(class), parser.pos),
+        }
+    }
+
+    fn class_of(pattern: &str) -> NfaArrow {
+        class_with(pattern, Flags::default()).0
+    }
+
+    fn escape(escape: &str) -> Result<(char, usize), (ErrorKind, usize)> {
+        parse_escape(escape.chars())
+    }
+
+    #[test]
+    fn test_parse_escape() {
+        for c in "\\.+*?()|[]{}^$-#&~/".chars() {
+            assert_eq!(escape(&c.to_string()), Ok((c, 1)));
+        }
+        assert_eq!(escape("n"), Ok(('\n', 1)));
+        assert_eq!(escape("tx"), Ok(('\t', 1)));
+        assert_eq!(escape("r"), Ok(('\r', 1)));
+        assert_eq!(escape("0"), Ok(('\0', 1)));
+        assert_eq!(escape(" "), Ok((' ', 1)));
+        assert_eq!(escape("x41"), Ok(('A', 3)));
+        assert_eq!(escape("xe9b"), Ok(('é', 3)));
+        assert_eq!(escape("x{1F600}"), Ok(('😀', 8)));
+        assert_eq!(escape("u{e9}"), Ok(('é', 5)));
+        assert_eq!(escape("u{10FFFF}"), Ok(('\u{10FFFF}', 9)));
+        assert_eq!(escape("q"), Err((BadEscape, 1)));
+        assert_eq!(escape(""), Err((TrailingEscape, 0)));
+        assert_eq!(escape("u{D800}"), Err((InvalidCodePoint, 7)));
+    }
+
+    #[test]
+    fn test_parse_nested_classes() {
+        let class = |ranges: Vec<(char, char)>| OneOf(CharClass::new(ranges));
+        assert_eq!(class_of("a-c[x-z]]"), class(vec![('a', 'c'), ('x', 'z')]));
+        assert_eq!(
+            class_of("[^a]]"),
+            OneOf(CharClass::from_chars(['a']).negate())
+        );
+        assert_eq!(class_of("[[a]b]c]"), class(vec![('a', 'c')]));
+        assert_eq!(class_of("a-z&&m-q]"), class(vec![('m', 'q')]));
+        assert_eq!(class_of("a-z--[aeiou]]"), class_of("b-df-hj-np-tv-z]"));
+        assert_eq!(class_of("a-f~~d-k]"), class(vec![('a', 'c'), ('g', 'k')]));
+        assert_eq!(
+            class_of("a-z&&m-q--o]"),
+            class(vec![('m', 'n'), ('p', 'q')])
+        );
+        assert_eq!(class_of("^a-z&&b]"), NotOneOf(CharClass::from_chars(['b'])));
+        assert_eq!(class_of("a&b]"), class(vec![('&', '&'), ('a', 'b')]));
+    }
 
-        match parse_inner(r"[bc]", None) {
+    #[test]
+    fn test_parse_class_ranges() {
+        let class = |ranges: Vec<(char, char)>| OneOf(CharClass::new(ranges));
+        assert_eq!(
+            class_of("a-z0-9_]"),
+            class(vec![('a', 'z'), ('0', '9'), ('_', '_')])
+        );
+        assert_eq!(
+            class_of("^a-cx]"),
+            NotOneOf(CharClass::new(vec![('a', 'c'), ('x', 'x')]))
+        );
+        assert_eq!(class_of("-a]"), class(vec![('-', '-'), ('a', 'a')]));
+        assert_eq!(class_of("a-]"), class(vec![('-', '-'), ('a', 'a')]));
+        assert_eq!(class_of("^-]"), NotOneOf(CharClass::new(vec![('-', '-')])));
+        assert_eq!(class_of("]-a]"), class(vec![(']', 'a')]));
+        assert_eq!(class_of("\\[-\\]]"), class(vec![('[', ']')]));
+        assert_eq!(
+            class_of("a\\-z]"),
+            class(vec![('a', 'a'), ('-', '-'), ('z', 'z')])
+        );
+        assert_eq!(class_of("!--]"), class(vec![('!', '-')]));
+        assert_eq!(class_of("a-a]"), class(vec![('a', 'a')]));
+        assert_eq!(class_of("à-é]"), class(vec![('à', 'é')]));
+        let ascii = Flags {
+            unicode: false,
+            ..Flags::default()
+        };
+        assert_eq!(
+            class_with("\\d.-]", ascii).0,
+            class(vec![('-', '.'), ('0', '9')])
+        );
+        assert_eq!(
+            class_of("\\p{Greek}\\pN]"),
+            OneOf(
+                CharClass::property("Greek")
+                    .unwrap()
+                    .union(&CharClass::property("N").unwrap())
+            )
+        );
+        assert_eq!(
+            class_of("\\P{L}]"),
+            OneOf(CharClass::property("L").unwrap().negate())
+        );
+        let posix = |name| CharClass::posix(name).unwrap();
+        assert_eq!(class_of("[:alpha:]]"), OneOf(posix("alpha")));
+        assert_eq!(
+            class_of("^[:digit:][:space:]_]"),
+            NotOneOf(
+                posix("digit")
+                    .union(&posix("space"))
+                    .union(&CharClass::from_chars(vec!['_']))
+            )
+        );
+        assert_eq!(class_of("[:^upper:]]"), OneOf(posix("upper").negate()));
+        assert_eq!(class_of("[:a]]"), class(vec![(':', ':'), ('a', 'a')]));
+        assert_eq!(class_of("\\[]"), class(vec![('[', '[')]));
+        assert_eq!(
+            class_with("^\\D]", ascii).0,
+            NotOneOf(CharClass::new(vec![('0', '9')]).negate())
+        );
+        assert_eq!(class_with("a-c]x", Flags::default()).1, 5);
+        assert_eq!(class_with("é-ü]x", Flags::default()).1, 7);
+    }
+
+    #[test]
+    fn test_quantified_atoms_do_not_share_nodes() {
+        let graph = Graph::new(6)
+            .add_edge(0, Epsilon, 1)
+            .add_edge(0, Epsilon, 3)
+            .add_edge(1, Char('a'), 2)
+            .add_edge(2, Repeat, 1)
+            .add_edge(2, Epsilon, 3)
+            .add_edge(3, Epsilon, 4)
+            .add_edge(3, Epsilon, 6)
+            .add_edge(4, Char('b'), 5)
+            .add_edge(5, Repeat, 4)
+            .add_edge(5, Epsilon, 6);
+
+        match parse(r"a*b*") {
+            Err(e) => panic!("Failed to parse: {}", e),
+            Ok(res) => assert_eq!(res.nfa, graph),
+        }
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        match parse(r"(a)(?:b)(?P<first>c(?<second>d))|(?:e)(f)") {
+            Err(e) => panic!("Failed to parse: {}", e),
+            Ok(res) => assert_eq!(
+                res.groups,
+                vec![
+                    None,
+                    None,
+                    Some("first".to_string()),
+                    Some("second".to_string()),
+                    None
+                ]
+            ),
+        }
+    }
+
+    #[test]
+    fn test_parse_group_errors() {
+        assert!(parse(r"(?P<>a)").is_err());
+        assert!(parse(r"(?P<1a>a)").is_err());
+        assert!(parse(r"(?P<a-b>a)").is_err());
+        assert!(parse(r"(?P<name").is_err());
+        assert!(parse(r"(?<a>x)(?<a>y)").is_err());
+        assert!(parse(r"(?").is_err());
+        assert!(parse(r"(?:a").is_err());
+    }
+
+    /// The flags of the group that `group` follows the parenthesis of,
+    /// with the length of its prefix.
+    fn flags_of(group: &str) -> Result<(Option<Flags>, usize), ErrorKind> {
+        let pattern = format!("({}", group);
+        let mut parser = Parser::new(&pattern);
+        parser.bump();
+        let kind = parser
+            .parse_group_kind(0, Flags::default())
+            .map_err(|e| e.kind())?;
+        let len = parser.pos - 1;
+        Ok(match kind {
+            GroupKind::SetFlags(flags) | GroupKind::NonCapture(flags) => (Some(flags), len),
+            GroupKind::Capture(_) => (None, len),
+        })
+    }
+
+    #[test]
+    fn test_parse_flags() {
+        let case_insensitive = Flags {
+            case_insensitive: true,
+            ..Flags::default()
+        };
+        assert_eq!(flags_of("?i)a"), Ok((Some(case_insensitive), 3)));
+        assert_eq!(flags_of("?i:a)"), Ok((Some(case_insensitive), 3)));
+        assert_eq!(
+            flags_of("?is-i)"),
+            Ok((
+                Some(Flags {
+                    dot_matches_new_line: true,
+                    ..Flags::default()
+                }),
+                6
+            ))
+        );
+        assert_eq!(
+            flags_of("?msx)"),
+            Ok((
+                Some(Flags {
+                    multi_line: true,
+                    dot_matches_new_line: true,
+                    verbose: true,
+                    ..Flags::default()
+                }),
+                5
+            ))
+        );
+        assert_eq!(flags_of("?-i)"), Ok((Some(Flags::default()), 4)));
+        assert_eq!(
+            flags_of("?mR)"),
+            Ok((
+                Some(Flags {
+                    multi_line: true,
+                    crlf: true,
+                    ..Flags::default()
+                }),
+                4
+            ))
+        );
+        assert_eq!(flags_of("?U)"), Err(UnknownGroupFlag));
+        assert_eq!(flags_of("?i"), Err(UnclosedGroup));
+    }
+
+    #[test]
+    fn test_inline_flags_scope() {
+        let parsed = |pattern| parse(pattern).unwrap().nfa;
+        assert_eq!(parsed("(?i)a"), parsed("[aA]"));
+        assert_eq!(parsed("a(?i)b"), parsed("a[bB]"));
+        assert_eq!(parsed("(?i:a)b"), parsed("(?:[aA])b"));
+        assert_eq!(parsed("((?i)a)b"), parsed("([aA])b"));
+        assert_eq!(parsed("(?i)a(?-i)b"), parsed("[aA]b"));
+        assert_eq!(parsed("(?i)a(b)"), parsed("[aA]([bB])"));
+        assert_eq!(parsed("(?i)a(?-i:b)c"), parsed("[aA](?:b)[cC]"));
+        assert_eq!(parsed("(?i)a|b"), parsed("[aA]|[bB]"));
+        assert_eq!(parsed("a|(?i)b|c"), parsed("a|[bB]|[cC]"));
+    }
+
+    #[test]
+    fn test_verbose() {
+        let parsed = |pattern| parse(pattern).unwrap().nfa;
+        assert_eq!(parsed("(?x) a b c "), parsed("abc"));
+        assert_eq!(parsed("(?x)a # a comment\n b"), parsed("ab"));
+        assert_eq!(parsed("(?x)a +"), parsed("a+"));
+        assert_eq!(parsed("(?x)a\\ b"), parsed("a b"));
+        assert_eq!(parsed("(?x)a\\#b"), parsed("a#b"));
+        assert_eq!(parsed("(?x)[a b]"), parsed("[a b]"));
+        assert_eq!(parsed("(?x)a (?-x: b ) c"), parsed("a(?: b )c"));
+        assert_eq!(parsed("(?x)( a | b )"), parsed("(a|b)"));
+        assert_eq!(parsed("(?x)a#b"), parsed("a"));
+        assert_eq!(parsed("a #b(?x) c # d"), parsed("a #bc"));
+        assert_eq!(error_of("(?x)a # b\n )"), (UnopenedGroup, 11..12));
+    }
+
+    #[test]
+    fn test_parse_with_flags() {
+        let flags = Flags {
+            case_insensitive: true,
+            multi_line: true,
+            dot_matches_new_line: true,
+            ..Flags::default()
+        };
+        let graph = Graph::new(4)
+            .add_edge(0, MultiLineStart, 1)
+            .add_edge(1, OneOf(CharClass::from_chars(vec!['a', 'A'])), 2)
+            .add_edge(2, AnyChar, 3)
+            .add_edge(3, MultiLineEnd, 4);
+
+        match parse_with_flags(r"^a.$", flags) {
             Err(e) => panic!("Failed to parse: {}", e),
-            Ok(res) => assert_eq!(res, (graph, 4)),
+            Ok(res) => assert_eq!(res.nfa, graph),
+        }
+    }
+
+    #[test]
+    fn test_parse_ast() {
+        let literal = |c, case_insensitive, span| {
+            Ast::new(
+                AstKind::Literal {
+                    c,
+                    case_insensitive,
+                },
+                span,
+            )
+        };
+        let group = Ast::new(
+            AstKind::Group {
+                index: Some(1),
+                inner: Box::new(literal('ü', false, 3..5)),
+            },
+            2..6,
+        );
+        let repetition = Ast::new(
+            AstKind::Repetition {
+                inner: Box::new(group),
+                min: 1,
+                max: None,
+                greedy: false,
+            },
+            2..8,
+        );
+        let concat = Ast::new(
+            AstKind::Concat(vec![literal('é', false, 0..2), repetition]),
+            0..8,
+        );
+        let (ast, groups) = parse_ast("é(ü)+?|(?i)x", Flags::default()).unwrap();
+        assert_eq!(
+            ast,
+            Ast::new(
+                AstKind::Alternation(vec![concat, literal('x', true, 13..14)]),
+                0..14
+            )
+        );
+        assert_eq!(groups, vec![None, None]);
+        assert_eq!(
+            parse_ast("", Flags::default()).unwrap().0,
+            Ast::new(AstKind::Empty, 0..0)
+        );
+    }
+
+    fn error_of(pattern: &str) -> (ErrorKind, Range<usize>) {
+        match parse(pattern) {
+            Err(e) => (e.kind(), e.span()),
+            Ok(_) => panic!("Parsed invalid pattern {}", pattern),
         }
     }
+
+    #[test]
+    fn test_nesting_limit() {
+        let flags = Flags {
+            nesting_limit: 2,
+            ..Flags::default()
+        };
+        let error_with = |pattern| {
+            let error = parse_with_flags(pattern, flags).unwrap_err();
+            (error.kind(), error.span())
+        };
+        assert!(parse_with_flags("((a))(b)", flags).is_ok());
+        assert!(parse_with_flags("([a])", flags).is_ok());
+        assert!(parse_with_flags("((?i)a(?:b))", flags).is_ok());
+        assert_eq!(error_with("((é(a)))"), (NestingTooDeep, 4..5));
+        assert_eq!(error_with("([[a]])"), (NestingTooDeep, 2..3));
+        assert_eq!(error_with("[[[a]]]"), (NestingTooDeep, 2..3));
+        let deep = format!("{}a{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert_eq!(error_of(&deep), (NestingTooDeep, 250..251));
+        assert_eq!(error_of(&"[".repeat(100_000)), (NestingTooDeep, 250..251));
+        assert_eq!(error_of(&"(a|".repeat(100_000)), (NestingTooDeep, 750..751));
+    }
+
+    #[test]
+    fn test_parse_repetition() {
+        assert_eq!(parse_repetition("3}", 10), Ok((3, Some(3), 2)));
+        assert_eq!(parse_repetition("3,}a", 10), Ok((3, None, 3)));
+        assert_eq!(parse_repetition("0,10}", 10), Ok((0, Some(10), 5)));
+        assert_eq!(parse_repetition("2,2}", 10), Ok((2, Some(2), 4)));
+        assert_eq!(parse_repetition("11}", 10), Err(RepetitionTooLarge));
+        assert_eq!(parse_repetition("1,11}", 10), Err(RepetitionTooLarge));
+        assert_eq!(parse_repetition("1, 2}", 10), Err(InvalidRepetition));
+    }
+
+    #[test]
+    fn test_repetition_limit() {
+        let flags = Flags {
+            repetition_limit: 2000,
+            ..Flags::default()
+        };
+        assert!(parse_with_flags("a{2000}", flags).is_ok());
+        assert!(parse_with_flags("a{2001}", flags).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(error_of("a**"), (DanglingQuantifier, 2..3));
+        assert_eq!(error_of("(*)"), (DanglingQuantifier, 1..2));
+        assert_eq!(error_of("ab)"), (UnopenedGroup, 2..3));
+        assert_eq!(error_of("a(b(c)"), (UnclosedGroup, 1..2));
+        assert_eq!(error_of("((a|b"), (UnclosedGroup, 0..1));
+        assert_eq!(error_of("a[bc"), (UnclosedClass, 1..2));
+        assert_eq!(error_of("a|[]"), (UnclosedClass, 2..3));
+        assert_eq!(error_of("ab\\"), (TrailingEscape, 2..3));
+        assert_eq!(error_of("(a)\\q"), (BadEscape, 3..5));
+        assert_eq!(error_of("a(?y)"), (UnknownGroupFlag, 3..4));
+        assert_eq!(error_of("a(?i-y:b)"), (UnknownGroupFlag, 5..6));
+        assert_eq!(error_of("(?)"), (MissingGroupFlag, 2..3));
+        assert_eq!(error_of("(?i-)"), (MissingGroupFlag, 4..5));
+        assert_eq!(error_of("(?-:a)"), (MissingGroupFlag, 3..4));
+        assert_eq!(error_of("(?i--s)"), (UnknownGroupFlag, 4..5));
+        assert_eq!(error_of("(?i-s-m)"), (UnknownGroupFlag, 5..6));
+        assert_eq!(error_of("a(?is"), (UnclosedGroup, 1..2));
+        assert_eq!(error_of("(?i)*"), (DanglingQuantifier, 4..5));
+        assert_eq!(error_of("(?P<a>x)(?P<a>y)"), (DuplicateGroupName, 12..13));
+        assert_eq!(error_of("(?<1>x)"), (InvalidGroupName, 3..4));
+        assert_eq!(error_of("(?<ab"), (UnclosedGroupName, 3..5));
+        assert_eq!(error_of("é(é\\q)"), (BadEscape, 5..7));
+        assert_eq!(error_of("{2}"), (DanglingQuantifier, 0..3));
+        assert_eq!(error_of("a{2"), (UnclosedRepetition, 1..3));
+        assert_eq!(error_of("a{}"), (InvalidRepetition, 1..3));
+        assert_eq!(error_of("a{,2}b"), (InvalidRepetition, 1..5));
+        assert_eq!(error_of("a{1,2,3}"), (InvalidRepetition, 1..8));
+        assert_eq!(error_of("a{x}"), (InvalidRepetition, 1..4));
+        assert_eq!(error_of("a{3,1}"), (InvalidRepetitionRange, 1..6));
+        assert_eq!(error_of("a{1001}"), (RepetitionTooLarge, 1..7));
+        assert_eq!(
+            error_of("a{99999999999999999999}"),
+            (RepetitionTooLarge, 1..23)
+        );
+        assert_eq!(error_of("a{2}{3,1}"), (DanglingQuantifier, 4..9));
+        assert_eq!(error_of("a[b-az]"), (InvalidClassRange, 2..5));
+        assert_eq!(error_of("é[^é-a]"), (InvalidClassRange, 4..8));
+        assert_eq!(error_of("[a-"), (UnclosedClass, 0..1));
+        assert_eq!(error_of("[a-\\"), (UnclosedClass, 0..1));
+        assert_eq!(error_of("[\\d-z]"), (InvalidClassRange, 1..5));
+        assert_eq!(error_of("[a-\\w]"), (InvalidClassRange, 1..5));
+        assert_eq!(error_of("\\q"), (BadEscape, 0..2));
+        assert_eq!(error_of("[\\q]"), (BadEscape, 1..3));
+        assert_eq!(error_of("a\\xZ1"), (InvalidHexEscape, 1..5));
+        assert_eq!(error_of("a\\x1"), (InvalidHexEscape, 1..4));
+        assert_eq!(error_of("a\\x"), (InvalidHexEscape, 1..3));
+        assert_eq!(error_of("\\u0041"), (InvalidHexEscape, 0..2));
+        assert_eq!(error_of("\\x{}"), (InvalidHexEscape, 0..4));
+        assert_eq!(error_of("\\x{12g4}"), (InvalidHexEscape, 0..6));
+        assert_eq!(error_of("\\u{1234567}"), (InvalidHexEscape, 0..11));
+        assert_eq!(error_of("a\\u{41"), (UnclosedHexEscape, 1..6));
+        assert_eq!(error_of("\\u{110000}"), (InvalidCodePoint, 0..10));
+        assert_eq!(error_of("é\\x{D800}b"), (InvalidCodePoint, 2..10));
+        assert_eq!(error_of("[a-\\u{DFFF}]"), (InvalidCodePoint, 3..11));
+        assert_eq!(error_of("a\\p{Foo}"), (UnknownProperty, 1..8));
+        assert_eq!(error_of("\\pX"), (UnknownProperty, 0..3));
+        assert_eq!(error_of("\\p{Greek"), (UnclosedProperty, 0..8));
+        assert_eq!(error_of("a\\P"), (UnclosedProperty, 1..3));
+        assert_eq!(error_of("[a\\p{Foo}]"), (UnknownProperty, 2..9));
+        assert_eq!(error_of("[\\p{L]"), (UnclosedProperty, 1..6));
+        assert_eq!(error_of("a[[:word:]]"), (UnknownPosixClass, 2..10));
+        assert_eq!(error_of("a[b[c]"), (UnclosedClass, 1..2));
+        assert_eq!(error_of("a[b[c"), (UnclosedClass, 3..4));
+        assert_eq!(error_of("[&&a]"), (MissingClassOperand, 1..3));
+        assert_eq!(error_of("[a--]"), (InvalidClassRange, 1..4));
+        assert_eq!(error_of("[a&&]"), (MissingClassOperand, 2..4));
+        assert_eq!(error_of("[a&&~~b]"), (MissingClassOperand, 4..6));
+        assert_eq!(error_of("[a&&b]"), (EmptyClass, 0..1));
+        assert_eq!(error_of("a[^\\s\\S]"), (EmptyClass, 1..2));
+        assert_eq!(error_of("[a-[b]]"), (InvalidClassRange, 1..6));
+        assert_eq!(error_of("[x[:^Alpha:]]"), (UnknownPosixClass, 2..12));
+    }
 }
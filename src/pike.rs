@@ -0,0 +1,357 @@
+use std::collections::{HashMap, HashSet};
+use std::iter::once;
+
+use crate::nfa::NfaArrow::*;
+use crate::nfa::{CharKind, NFA};
+
+/// Capture slots of a thread: the start and end of every group, the whole match being group 0.
+pub type Slots = Vec<Option<usize>>;
+
+/// Finds the leftmost match that starts at or after the byte offset `from`,
+/// or exactly at `from` if the search is `anchored`. Returns the byte offsets of the match.
+pub fn find_at(nfa: &NFA, text: &str, from: usize, anchored: bool) -> Option<(usize, usize)> {
+    let slots = captures_at(nfa, text, from, anchored, 2)?;
+    Some((slots[0]?, slots[1]?))
+}
+
+/// Same as `find_at`, but also fills `slot_count` capture slots for the match.
+/// Groups without a slot are not tracked.
+///
+/// This is a Pike VM: all threads advance through the text in lockstep, ordered by priority.
+/// Edges that come first in the NFA have higher priority, which gives leftmost-first
+/// (Perl-like) semantics instead of the leftmost-longest one. As every thread carries
+/// its own slots, a repeated group ends up holding its last iteration.
+/// As in Perl, an iteration of a loop that matches nothing ends the loop,
+/// so `(a|)+` on "aa" ends with an empty third iteration.
+pub fn captures_at(
+    nfa: &NFA,
+    text: &str,
+    from: usize,
+    anchored: bool,
+    slot_count: usize,
+) -> Option<Slots> {
+    let positions = text[from..]
+        .char_indices()
+        .map(|(i, c)| (from + i, Some(c)))
+        .chain(once((text.len(), None)));
+    let mut current = Threads::default();
+    let mut found = None;
+
+    for (at, c) in positions {
+        // A new thread is started at every position until a match is found,
+        // with a lower priority than the threads that started earlier.
+        if found.is_none() && (at == from || !anchored) {
+            let mut slots = vec![None; slot_count];
+            slots[0] = Some(at);
+            current.add(nfa, text, 0, at, slots);
+        }
+        if current.threads.is_empty() && (found.is_some() || anchored) {
+            break;
+        }
+
+        let mut next = Threads::default();
+        for thread in current.threads {
+            match thread {
+                Thread::Match { mut slots } => {
+                    // Everything after this thread has a lower priority.
+                    slots[1] = Some(at);
+                    found = Some(slots);
+                    break;
+                }
+                Thread::Step { node, edge, slots } => {
+                    let edge = &nfa.edges[&node][edge];
+                    if let Some(c) = c.filter(|&c| edge.ch.accepts(c)) {
+                        next.add(nfa, text, edge.to, at + c.len_utf8(), slots);
+                    }
+                }
+            }
+        }
+        current = next;
+    }
+
+    found
+}
+
+enum Thread {
+    /// Waits for a character that can be consumed through the given edge of the node.
+    Step {
+        node: usize,
+        edge: usize,
+        slots: Slots,
+    },
+    Match {
+        slots: Slots,
+    },
+}
+
+enum Frame {
+    Visit(usize, Slots),
+    Resume(usize, usize, Slots),
+    /// Leaves the loop iteration that the last `Repeat` arrow on the path started.
+    EndIteration,
+}
+
+#[derive(Default)]
+struct Threads {
+    threads: Vec<Thread>,
+    visited: HashSet<usize>,
+}
+
+impl Threads {
+    /// Adds the threads reachable from `node` without consuming any characters.
+    /// The edges are explored depth first and in order, so that the resulting threads are
+    /// sorted by priority. An explicit stack is used to not overflow on long epsilon chains.
+    ///
+    /// A path that comes back to one of its own nodes went through a loop iteration
+    /// that matched nothing. That iteration ends the loop: the path goes on with the edges
+    /// that follow the `Repeat` arrow of the loop, at its own priority.
+    fn add(&mut self, nfa: &NFA, text: &str, node: usize, at: usize, slots: Slots) {
+        let mut stack = vec![Frame::Visit(node, slots)];
+        let previous = text[..at].chars().next_back().map(CharKind::of);
+        let next = text[at..].chars().next().map(CharKind::of);
+        // The nodes of the current path, with the next edge to explore
+        // and the number of loop iterations started on the path before them.
+        let mut path: HashMap<usize, (usize, usize)> = HashMap::new();
+        // The loop nodes of the iterations started on the path,
+        // with the edge that follows their `Repeat` arrow.
+        let mut loops: Vec<(usize, usize)> = vec![];
+
+        while let Some(frame) = stack.pop() {
+            match frame {
+                Frame::Visit(node, slots) => {
+                    if !self.visited.insert(node) {
+                        let empty_loop = path.get(&node).and_then(|&(_, depth)| loops.get(depth));
+                        if let Some(&(loop_node, edge)) = empty_loop {
+                            stack.push(Frame::Resume(loop_node, edge, slots));
+                        }
+                        continue;
+                    }
+                    if node == nfa.final_node {
+                        self.threads.push(Thread::Match { slots });
+                        continue;
+                    }
+                    path.insert(node, (0, loops.len()));
+                    stack.push(Frame::Resume(node, 0, slots));
+                }
+                Frame::Resume(node, edge, slots) => {
+                    // The edges were already explored by a path that ended the loop early.
+                    let depth = match path.get(&node) {
+                        Some(&(next_edge, depth)) if next_edge == edge => depth,
+                        _ => continue,
+                    };
+                    let e = match nfa.edges.get(&node).and_then(|edges| edges.get(edge)) {
+                        None => {
+                            path.remove(&node);
+                            continue;
+                        }
+                        Some(e) => e,
+                    };
+                    path.insert(node, (edge + 1, depth));
+                    stack.push(Frame::Resume(node, edge + 1, slots.clone()));
+
+                    match e.ch {
+                        Epsilon => stack.push(Frame::Visit(e.to, slots)),
+                        Repeat => {
+                            loops.push((node, edge + 1));
+                            stack.push(Frame::EndIteration);
+                            stack.push(Frame::Visit(e.to, slots));
+                        }
+                        Save(slot) => {
+                            let mut slots = slots;
+                            if slot < slots.len() {
+                                slots[slot] = Some(at);
+                            }
+                            stack.push(Frame::Visit(e.to, slots))
+                        }
+                        ref assertion if assertion.is_assertion() => {
+                            if assertion.holds(previous, next) {
+                                stack.push(Frame::Visit(e.to, slots))
+                            }
+                        }
+                        _ => self.threads.push(Thread::Step { node, edge, slots }),
+                    }
+                }
+                Frame::EndIteration => {
+                    loops.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod pike_test {
+    use super::*;
+    use crate::error::RegexError;
+    use crate::parser::parse;
+    use crate::regex_tests;
+
+    fn check_for_pattern(pattern: &str, string: &str) -> Result<bool, RegexError> {
+        let graph = parse(pattern)?.nfa;
+        Ok(find_at(&graph, string, 0, false).is_some())
+    }
+
+    fn find(pattern: &str, string: &str) -> Option<(usize, usize)> {
+        find_at(&parse(pattern).unwrap().nfa, string, 0, false)
+    }
+
+    regex_tests!(check_for_pattern);
+
+    #[test]
+    fn test_leftmost() {
+        assert_eq!(find("b+", "abbcbbb"), Some((1, 3)));
+        assert_eq!(find("a|ab", "xab"), Some((1, 2)));
+        assert_eq!(find("ab|a", "xab"), Some((1, 3)));
+        assert_eq!(find("x*", "abc"), Some((0, 0)));
+        assert_eq!(find("c$", "cbc"), Some((2, 3)));
+        assert_eq!(find("^c", "bc"), None);
+    }
+
+    #[test]
+    fn test_greedy() {
+        assert_eq!(find("a*", "aaab"), Some((0, 3)));
+        assert_eq!(find("a?a", "aa"), Some((0, 2)));
+        assert_eq!(find("(a|b)+", "cabbac"), Some((1, 5)));
+        assert_eq!(find("a.*c", "abcbc"), Some((0, 5)));
+        assert_eq!(find("a{2,3}", "baaaa"), Some((1, 4)));
+        assert_eq!(find("(ab){2,}", "abababa"), Some((0, 6)));
+        assert_eq!(find("(?:|.b)*\\w", " 1"), Some((1, 2)));
+        assert_eq!(find("(?:a|b+)+", "xbba"), Some((1, 4)));
+    }
+
+    #[test]
+    fn test_lazy() {
+        assert_eq!(find("a*?", "aaab"), Some((0, 0)));
+        assert_eq!(find("a+?", "aaab"), Some((0, 1)));
+        assert_eq!(find("a??", "aa"), Some((0, 0)));
+        assert_eq!(find("a??a", "aa"), Some((0, 1)));
+        assert_eq!(find("a.*?c", "abcbc"), Some((0, 3)));
+        assert_eq!(find("a+?b", "aaab"), Some((0, 4)));
+        assert_eq!(find("a{2,3}?", "baaaa"), Some((1, 3)));
+        assert_eq!(find("a{2,}?", "baaaa"), Some((1, 3)));
+        assert_eq!(find("a{0,2}?b", "aab"), Some((0, 3)));
+        assert_eq!(find("(ab)*?c", "ababc"), Some((0, 5)));
+        assert_eq!(find("\"(.*?)\"", "say \"hi\" and \"bye\""), Some((4, 8)));
+    }
+
+    #[test]
+    fn test_word_boundary() {
+        assert_eq!(find("\\bis\\b", "this is"), Some((5, 7)));
+        assert_eq!(find("\\Bis\\b", "this is"), Some((2, 4)));
+        assert_eq!(find("\\b", "  ab"), Some((2, 2)));
+        assert_eq!(find("\\b\\w+\\b", "été, là"), Some((0, 5)));
+        assert_eq!(find("\\Aa", "aa"), Some((0, 1)));
+        assert_eq!(find("a\\z", "aa"), Some((1, 2)));
+    }
+
+    #[test]
+    fn test_byte_offsets() {
+        assert_eq!(find("é+", "aéé!"), Some((1, 5)));
+        assert_eq!(
+            find_at(&parse("b").unwrap().nfa, "bab", 1, false),
+            Some((2, 3))
+        );
+        assert_eq!(find_at(&parse("^b").unwrap().nfa, "bab", 2, false), None);
+    }
+
+    fn captures(pattern: &str, string: &str) -> Option<Slots> {
+        let parsed = parse(pattern).unwrap();
+        captures_at(&parsed.nfa, string, 0, false, 2 * parsed.groups.len())
+    }
+
+    #[test]
+    fn test_captures() {
+        assert_eq!(
+            captures("(a+)(b)?c", "xaac"),
+            Some(vec![Some(1), Some(4), Some(1), Some(3), None, None])
+        );
+        assert_eq!(
+            captures("a(b(c))|(d)", "abc"),
+            Some(vec![
+                Some(0),
+                Some(3),
+                Some(1),
+                Some(3),
+                Some(2),
+                Some(3),
+                None,
+                None
+            ])
+        );
+        assert_eq!(
+            captures("(.*)c(.*)", "abcde"),
+            Some(vec![Some(0), Some(5), Some(0), Some(2), Some(3), Some(5)])
+        );
+        assert_eq!(captures("(a)", "b"), None);
+    }
+
+    #[test]
+    fn test_lazy_captures() {
+        assert_eq!(
+            captures("(a+?)(a*)", "aaa"),
+            Some(vec![Some(0), Some(3), Some(0), Some(1), Some(1), Some(3)])
+        );
+        assert_eq!(
+            captures("(.*?)c(.*)", "abcdc"),
+            Some(vec![Some(0), Some(5), Some(0), Some(2), Some(3), Some(5)])
+        );
+    }
+
+    #[test]
+    fn test_empty_iteration_ends_loop() {
+        // Like in Perl, an iteration that matches nothing leaves the loop
+        // before an iteration that goes on consuming.
+        assert_eq!(find("(.*?)+\\b", " A"), Some((0, 1)));
+        assert_eq!(find("(?i:B|\\pL*?)*", "bAS"), Some((0, 1)));
+        assert_eq!(find("(\\w*?)*[^a]", "aſk"), Some((0, 3)));
+        assert_eq!(find("(?:a*?)+?b", "aab"), Some((0, 3)));
+        assert_eq!(
+            captures("(a|)+", "aa"),
+            Some(vec![Some(0), Some(2), Some(2), Some(2)])
+        );
+    }
+
+    #[test]
+    fn test_captures_last_iteration() {
+        assert_eq!(
+            captures("([abc])*d", "abcd"),
+            Some(vec![Some(0), Some(4), Some(2), Some(3)])
+        );
+        assert_eq!(
+            captures("(a|(b))+", "ba"),
+            Some(vec![Some(0), Some(2), Some(1), Some(2), Some(0), Some(1)])
+        );
+    }
+
+    #[test]
+    fn test_anchored() {
+        let nfa = parse("b+").unwrap().nfa;
+        assert_eq!(find_at(&nfa, "abb", 0, true), None);
+        assert_eq!(find_at(&nfa, "abb", 1, true), Some((1, 3)));
+    }
+
+    #[test]
+    fn test_multi_line() {
+        let flags = crate::parser::Flags {
+            multi_line: true,
+            ..Default::default()
+        };
+        let nfa = crate::parser::parse_with_flags("^b+$", flags).unwrap().nfa;
+        assert_eq!(find_at(&nfa, "ab\nbb\nc", 0, false), Some((3, 5)));
+        assert_eq!(find_at(&nfa, "ab\nbb", 0, false), Some((3, 5)));
+        assert_eq!(find_at(&nfa, "ab\nbbc", 0, false), None);
+    }
+
+    #[test]
+    fn test_crlf() {
+        let flags = crate::parser::Flags {
+            multi_line: true,
+            crlf: true,
+            ..Default::default()
+        };
+        let nfa = crate::parser::parse_with_flags("^.*$", flags).unwrap().nfa;
+        assert_eq!(find_at(&nfa, "ab\r\ncd", 0, false), Some((0, 2)));
+        assert_eq!(find_at(&nfa, "ab\r\ncd", 2, false), Some((4, 6)));
+        assert_eq!(find_at(&nfa, "\r\ncd", 1, false), Some((2, 4)));
+    }
+}
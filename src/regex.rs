@@ -0,0 +1,663 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Index, Range};
+use std::sync::{Arc, Mutex};
+
+use crate::builder::RegexBuilder;
+use crate::dfa::DFA;
+use crate::error::RegexError;
+use crate::nfa::NFA;
+use crate::parser::Parsed;
+use crate::pike;
+use crate::pike::Slots;
+use crate::replace::Replacer;
+
+/// A compiled regular expression.
+///
+/// The pattern is parsed once in `Regex::new`, after that the regex can be used
+/// to test any number of strings. The underlying DFA is built lazily and its states
+/// are cached between calls, which is why it is kept behind a lock.
+pub struct Regex {
+    pattern: String,
+    nfa: NFA,
+    groups: Arc<Groups>,
+    dfa: Mutex<DFA>,
+    anchored: bool,
+}
+
+impl Regex {
+    /// Compiles the pattern with the default options, see `RegexBuilder` to change them.
+    pub fn new(pattern: &str) -> Result<Self, RegexError> {
+        RegexBuilder::new(pattern).build()
+    }
+
+    pub(crate) fn from_parsed(pattern: &str, parsed: Parsed, dfa: DFA, anchored: bool) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            nfa: parsed.nfa,
+            groups: Arc::new(Groups::new(parsed.groups)),
+            dfa: Mutex::new(dfa),
+            anchored,
+        }
+    }
+
+    /// Returns true if there is a match for the regex anywhere in the given text.
+    pub fn is_match(&self, text: &str) -> bool {
+        let mut dfa = match self.dfa.lock() {
+            Ok(dfa) => dfa,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        dfa.walk(text)
+    }
+
+    /// Returns the leftmost match in the text. When several matches start at the same position,
+    /// the one preferred by the pattern is chosen: alternatives are tried from left to right
+    /// and repetitions are greedy.
+    pub fn find<'t>(&self, text: &'t str) -> Option<Match<'t>> {
+        self.find_at(text, 0)
+    }
+
+    /// Returns an iterator over all non-overlapping matches in the text.
+    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> Matches<'r, 't> {
+        Matches {
+            regex: self,
+            search: Search::new(text),
+        }
+    }
+
+    /// Returns an iterator over the parts of the text separated by the matches of the regex.
+    ///
+    /// A match at the start or the end of the text produces an empty first or last part,
+    /// and an empty match splits the text between two characters.
+    pub fn split<'r, 't>(&'r self, text: &'t str) -> Split<'r, 't> {
+        Split {
+            matches: self.find_iter(text),
+            text,
+            last_end: 0,
+        }
+    }
+
+    /// Same as `split`, but returns at most `limit` parts,
+    /// the last one being the rest of the text.
+    pub fn splitn<'r, 't>(&'r self, text: &'t str, limit: usize) -> SplitN<'r, 't> {
+        SplitN {
+            split: self.split(text),
+            remaining: limit,
+        }
+    }
+
+    /// Returns the spans of all capture groups for the leftmost match in the text.
+    /// Group 0 is the whole match, the others are numbered by their opening parenthesis.
+    pub fn captures<'t>(&self, text: &'t str) -> Option<Captures<'t>> {
+        self.captures_at(text, 0)
+    }
+
+    /// Returns an iterator over the captures of all non-overlapping matches in the text.
+    pub fn captures_iter<'r, 't>(&'r self, text: &'t str) -> CaptureMatches<'r, 't> {
+        CaptureMatches {
+            regex: self,
+            search: Search::new(text),
+        }
+    }
+
+    /// Replaces the leftmost match in the text.
+    pub fn replace<'t, R: Replacer>(&self, text: &'t str, rep: R) -> Cow<'t, str> {
+        self.replacen(text, 1, rep)
+    }
+
+    /// Replaces all non-overlapping matches in the text.
+    pub fn replace_all<'t, R: Replacer>(&self, text: &'t str, rep: R) -> Cow<'t, str> {
+        self.replacen(text, 0, rep)
+    }
+
+    /// Replaces at most `limit` non-overlapping matches in the text, all of them if `limit` is 0.
+    /// The text is returned as it is when there is nothing to replace.
+    pub fn replacen<'t, R: Replacer>(
+        &self,
+        text: &'t str,
+        limit: usize,
+        mut rep: R,
+    ) -> Cow<'t, str> {
+        let mut result = String::new();
+        let mut last_end = 0;
+        let mut replaced = 0;
+
+        for caps in self.captures_iter(text) {
+            if limit > 0 && replaced >= limit {
+                break;
+            }
+            let m = caps.span();
+            result.push_str(&text[last_end..m.start]);
+            rep.append(&caps, &mut result);
+            last_end = m.end;
+            replaced += 1;
+        }
+
+        if replaced == 0 {
+            return Cow::Borrowed(text);
+        }
+        result.push_str(&text[last_end..]);
+        Cow::Owned(result)
+    }
+
+    /// Returns the number of capture groups, including the implicit group 0.
+    pub fn captures_len(&self) -> usize {
+        self.groups.names.len()
+    }
+
+    /// Returns the names of all capture groups in order, `None` for the unnamed ones.
+    /// The first item is always `None` as it stands for the whole match.
+    pub fn capture_names(&self) -> impl Iterator<Item = Option<&str>> {
+        self.groups.names.iter().map(|name| name.as_deref())
+    }
+
+    fn find_at<'t>(&self, text: &'t str, start: usize) -> Option<Match<'t>> {
+        pike::find_at(&self.nfa, text, start, self.anchored).map(|(start, end)| Match {
+            text,
+            start,
+            end,
+        })
+    }
+
+    fn captures_at<'t>(&self, text: &'t str, start: usize) -> Option<Captures<'t>> {
+        let slots = pike::captures_at(
+            &self.nfa,
+            text,
+            start,
+            self.anchored,
+            2 * self.captures_len(),
+        )?;
+        Some(Captures {
+            text,
+            slots,
+            groups: self.groups.clone(),
+        })
+    }
+
+    /// Returns the pattern this regex was compiled from.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Regex({:?})", self.pattern)
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+/// A single match of a regex in a text. The positions are byte offsets in the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'t> {
+    text: &'t str,
+    start: usize,
+    end: usize,
+}
+
+impl<'t> Match<'t> {
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn as_str(&self) -> &'t str {
+        &self.text[self.range()]
+    }
+}
+
+/// The capture groups of a single match, created by `Regex::captures`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captures<'t> {
+    text: &'t str,
+    slots: Slots,
+    groups: Arc<Groups>,
+}
+
+impl<'t> Captures<'t> {
+    /// Returns the match of the given group, or `None` if the group did not take part in the match.
+    pub fn get(&self, i: usize) -> Option<Match<'t>> {
+        match (self.slots.get(2 * i)?, self.slots.get(2 * i + 1)?) {
+            (Some(start), Some(end)) => Some(Match {
+                text: self.text,
+                start: *start,
+                end: *end,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the match of the group with the given name, or `None` if there is no such group
+    /// or it did not take part in the match.
+    pub fn name(&self, name: &str) -> Option<Match<'t>> {
+        self.get(*self.groups.indices.get(name)?)
+    }
+
+    /// Returns the number of groups, including the ones that did not take part in the match.
+    pub fn len(&self) -> usize {
+        self.slots.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn iter<'c>(&'c self) -> impl Iterator<Item = Option<Match<'t>>> + 'c {
+        (0..self.len()).map(move |i| self.get(i))
+    }
+}
+
+impl<'t> Index<usize> for Captures<'t> {
+    type Output = str;
+
+    /// Returns the text of the given group.
+    /// Panics if the group did not take part in the match.
+    fn index(&self, i: usize) -> &str {
+        self.get(i)
+            .map(|m| m.as_str())
+            .unwrap_or_else(|| panic!("no group at index {}", i))
+    }
+}
+
+impl<'t, 'n> Index<&'n str> for Captures<'t> {
+    type Output = str;
+
+    /// Returns the text of the group with the given name.
+    /// Panics if there is no such group or it did not take part in the match.
+    fn index(&self, name: &'n str) -> &str {
+        self.name(name)
+            .map(|m| m.as_str())
+            .unwrap_or_else(|| panic!("no group named '{}'", name))
+    }
+}
+
+/// Names of the capture groups of a regex, shared with all of its `Captures`.
+#[derive(Debug, PartialEq, Eq)]
+struct Groups {
+    names: Vec<Option<String>>,
+    indices: HashMap<String, usize>,
+}
+
+impl Groups {
+    fn new(names: Vec<Option<String>>) -> Self {
+        let indices = names
+            .iter()
+            .enumerate()
+            .filter_map(|(i, name)| Some((name.clone()?, i)))
+            .collect();
+        Self { names, indices }
+    }
+}
+
+/// Iterator over the non-overlapping matches of a regex, created by `Regex::find_iter`.
+pub struct Matches<'r, 't> {
+    regex: &'r Regex,
+    search: Search<'t>,
+}
+
+impl<'r, 't> Iterator for Matches<'r, 't> {
+    type Item = Match<'t>;
+
+    fn next(&mut self) -> Option<Match<'t>> {
+        let regex = self.regex;
+        self.search.next(|text, start| regex.find_at(text, start))
+    }
+}
+
+/// Iterator over the captures of the non-overlapping matches of a regex,
+/// created by `Regex::captures_iter`.
+pub struct CaptureMatches<'r, 't> {
+    regex: &'r Regex,
+    search: Search<'t>,
+}
+
+impl<'r, 't> Iterator for CaptureMatches<'r, 't> {
+    type Item = Captures<'t>;
+
+    fn next(&mut self) -> Option<Captures<'t>> {
+        let regex = self.regex;
+        self.search
+            .next(|text, start| regex.captures_at(text, start))
+    }
+}
+
+/// Iterator over the parts of a text between the matches of a regex, created by `Regex::split`.
+pub struct Split<'r, 't> {
+    matches: Matches<'r, 't>,
+    text: &'t str,
+    last_end: usize,
+}
+
+impl<'r, 't> Split<'r, 't> {
+    fn rest(&mut self) -> Option<&'t str> {
+        if self.last_end > self.text.len() {
+            return None;
+        }
+        let rest = &self.text[self.last_end..];
+        self.last_end = self.text.len() + 1;
+        Some(rest)
+    }
+}
+
+impl<'r, 't> Iterator for Split<'r, 't> {
+    type Item = &'t str;
+
+    fn next(&mut self) -> Option<&'t str> {
+        match self.matches.next() {
+            None => self.rest(),
+            Some(m) => {
+                let part = &self.text[self.last_end..m.start];
+                self.last_end = m.end;
+                Some(part)
+            }
+        }
+    }
+}
+
+/// Iterator over at most a given number of parts of a text, created by `Regex::splitn`.
+pub struct SplitN<'r, 't> {
+    split: Split<'r, 't>,
+    remaining: usize,
+}
+
+impl<'r, 't> Iterator for SplitN<'r, 't> {
+    type Item = &'t str;
+
+    fn next(&mut self) -> Option<&'t str> {
+        match self.remaining {
+            0 => None,
+            1 => {
+                self.remaining = 0;
+                self.split.rest()
+            }
+            _ => {
+                self.remaining -= 1;
+                self.split.next()
+            }
+        }
+    }
+}
+
+/// Progress of a search for consecutive matches in a text.
+///
+/// An empty match is never reported right where the previous match ended,
+/// so a pattern like `a*` yields "aa" and "" for the text "aab", not an extra "" after "aa".
+struct Search<'t> {
+    text: &'t str,
+    last_end: usize,
+    last_match: Option<usize>,
+}
+
+impl<'t> Search<'t> {
+    fn new(text: &'t str) -> Self {
+        Self {
+            text,
+            last_end: 0,
+            last_match: None,
+        }
+    }
+
+    fn next<T: Spanned>(&mut self, find_at: impl Fn(&'t str, usize) -> Option<T>) -> Option<T> {
+        loop {
+            if self.last_end > self.text.len() {
+                return None;
+            }
+
+            let found = find_at(self.text, self.last_end)?;
+            let m = found.span();
+            if m.start == m.end {
+                // Step over the next character, otherwise the same empty match is found forever.
+                self.last_end = next_char_boundary(self.text, m.end);
+                if self.last_match == Some(m.end) {
+                    continue;
+                }
+            } else {
+                self.last_end = m.end;
+            }
+
+            self.last_match = Some(m.end);
+            return Some(found);
+        }
+    }
+}
+
+trait Spanned {
+    fn span(&self) -> Range<usize>;
+}
+
+impl<'t> Spanned for Match<'t> {
+    fn span(&self) -> Range<usize> {
+        self.range()
+    }
+}
+
+impl<'t> Spanned for Captures<'t> {
+    fn span(&self) -> Range<usize> {
+        self.get(0).map_or(0..0, |m| m.range())
+    }
+}
+
+fn next_char_boundary(text: &str, at: usize) -> usize {
+    at + text[at..].chars().next().map_or(1, char::len_utf8)
+}
+
+#[cfg(test)]
+mod regex_test {
+    use super::*;
+    use crate::error::RegexError;
+    use crate::replace::Template;
+    use crate::{regex_tests, split_tests};
+
+    fn check_for_pattern(pattern: &str, string: &str) -> Result<bool, RegexError> {
+        Ok(Regex::new(pattern)?.is_match(string))
+    }
+
+    regex_tests!(check_for_pattern);
+
+    #[test]
+    fn test_reuse() {
+        let regex = Regex::new("^a[bc]+$").unwrap();
+        assert!(regex.is_match("abcb"));
+        assert!(!regex.is_match("abcbd"));
+        assert!(regex.is_match("ac"));
+        assert!(!regex.is_match("a"));
+    }
+
+    #[test]
+    fn test_shared_between_threads() {
+        let regex = std::sync::Arc::new(Regex::new("x+y").unwrap());
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let regex = regex.clone();
+                std::thread::spawn(move || regex.is_match(&format!("{}xxy", i)))
+            })
+            .collect();
+
+        for handle in handles {
+            assert!(handle.join().unwrap());
+        }
+    }
+
+    fn find_all<'t>(pattern: &str, text: &'t str) -> Vec<(usize, usize, &'t str)> {
+        Regex::new(pattern)
+            .unwrap()
+            .find_iter(text)
+            .map(|m| (m.start(), m.end(), m.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_find() {
+        let regex = Regex::new("[bc]+d").unwrap();
+        let m = regex.find("abcbdxbd").unwrap();
+        assert_eq!((m.start(), m.end(), m.as_str()), (1, 5, "bcbd"));
+        assert_eq!(m.range(), 1..5);
+        assert_eq!(regex.find("abc"), None);
+    }
+
+    #[test]
+    fn test_find_iter() {
+        assert_eq!(find_all("a+", "baabaaa"), vec![(1, 3, "aa"), (4, 7, "aaa")]);
+        assert_eq!(find_all("x", "abc"), vec![]);
+        assert_eq!(find_all("^a", "aaa"), vec![(0, 1, "a")]);
+    }
+
+    #[test]
+    fn test_find_iter_lazy() {
+        assert_eq!(
+            find_all("\".*?\"", r#"a "b" c "d" e"#),
+            vec![(2, 5, "\"b\""), (8, 11, "\"d\"")]
+        );
+        assert_eq!(
+            find_all("\".*\"", r#"a "b" c "d""#),
+            vec![(2, 11, r#""b" c "d""#)]
+        );
+        assert_eq!(
+            find_all("a+?", "aaa"),
+            vec![(0, 1, "a"), (1, 2, "a"), (2, 3, "a")]
+        );
+        assert_eq!(
+            find_all("<.+?>", "<a><b>"),
+            vec![(0, 3, "<a>"), (3, 6, "<b>")]
+        );
+    }
+
+    #[test]
+    fn test_find_iter_empty_matches() {
+        assert_eq!(find_all("a*", "aab"), vec![(0, 2, "aa"), (3, 3, "")]);
+        assert_eq!(find_all("", "ab"), vec![(0, 0, ""), (1, 1, ""), (2, 2, "")]);
+        assert_eq!(
+            find_all("b*", "aéb"),
+            vec![(0, 0, ""), (1, 1, ""), (3, 4, "b")]
+        );
+    }
+
+    #[test]
+    fn test_find_byte_offsets() {
+        let text = "ça va, ça va";
+        let matches: Vec<_> = Regex::new("ça").unwrap().find_iter(text).collect();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(&text[matches[1].range()], "ça");
+        assert_eq!(matches[1].start(), 8);
+    }
+
+    #[test]
+    fn test_captures() {
+        let regex = Regex::new("(x+)?([01]+)-([012]+)").unwrap();
+        let caps = regex.captures("from 10-20").unwrap();
+        assert_eq!(caps.len(), 4);
+        assert_eq!(&caps[0], "10-20");
+        assert_eq!(caps.get(1), None);
+        assert_eq!(&caps[2], "10");
+        assert_eq!(caps.get(3).unwrap().range(), 8..10);
+        assert_eq!(caps.get(4), None);
+        assert_eq!(regex.captures_len(), 4);
+        assert!(regex.captures("from -").is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_captures_index_of_missing_group() {
+        let caps = Regex::new("(a)|(b)").unwrap().captures("b").unwrap();
+        let _ = &caps[1];
+    }
+
+    #[test]
+    fn test_captures_iter() {
+        let regex = Regex::new("([abc])([12]*)").unwrap();
+        let found: Vec<_> = regex
+            .captures_iter("a1 b c22")
+            .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "".to_string()),
+                ("c".to_string(), "22".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_named_captures() {
+        let regex = Regex::new("(?P<key>[abc]+)=(?:(?<value>[xyz]+)|-)").unwrap();
+        assert_eq!(
+            regex.capture_names().collect::<Vec<_>>(),
+            vec![None, Some("key"), Some("value")]
+        );
+
+        let caps = regex.captures("ab=zz").unwrap();
+        assert_eq!(&caps["key"], "ab");
+        assert_eq!(caps.name("value").unwrap().range(), 3..5);
+        assert_eq!(&caps[2], "zz");
+        assert_eq!(caps.name("missing"), None);
+
+        let caps = regex.captures("c=-").unwrap();
+        assert_eq!(&caps["key"], "c");
+        assert_eq!(caps.name("value"), None);
+    }
+
+    fn split(pattern: &str, text: &str, limit: Option<usize>) -> Vec<String> {
+        let regex = Regex::new(pattern).unwrap();
+        let parts: Vec<_> = match limit {
+            None => regex.split(text).collect(),
+            Some(limit) => regex.splitn(text, limit).collect(),
+        };
+        parts.into_iter().map(String::from).collect()
+    }
+
+    split_tests!(split);
+
+    fn template(template: &str) -> Template {
+        Template::new(template).unwrap()
+    }
+
+    #[test]
+    fn test_replace() {
+        let regex = Regex::new("(?P<word>[abc]+)=([xyz]+)").unwrap();
+        let text = "ab=x, c=yz, a=b";
+        assert_eq!(regex.replace(text, template("$2=$1")), "x=ab, c=yz, a=b");
+        assert_eq!(
+            regex.replace_all(text, template("${2}:${word}")),
+            "x:ab, yz:c, a=b"
+        );
+        assert_eq!(
+            regex.replacen(text, 2, template("[$0] $$$3")),
+            "[ab=x] $, [c=yz] $, a=b"
+        );
+        assert!(matches!(
+            regex.replace_all("none", template("$1")),
+            Cow::Borrowed("none")
+        ));
+    }
+
+    #[test]
+    fn test_replace_with_closure() {
+        let regex = Regex::new("([abc])([xyz]?)").unwrap();
+        let replaced = regex.replace_all("ax b cz", |caps: &Captures| {
+            format!("{}{}", caps[2].to_uppercase(), caps[1].to_uppercase())
+        });
+        assert_eq!(replaced, "XA B ZC");
+    }
+
+    #[test]
+    fn test_replace_empty_matches() {
+        let regex = Regex::new("x*").unwrap();
+        assert_eq!(regex.replace_all("abxc", template("-")), "-a-b-c-");
+        assert_eq!(regex.replace("abc", template("")), "abc");
+    }
+}
@@ -0,0 +1,186 @@
+use std::str::FromStr;
+
+use crate::parser::is_valid_group_name;
+use crate::regex::Captures;
+
+/// Produces the replacement for every match in `Regex::replace` and friends.
+///
+/// It is implemented for `Template` and for closures that build the replacement
+/// from the captures of the match.
+pub trait Replacer {
+    /// Appends the replacement for the given match to `dst`.
+    fn append(&mut self, caps: &Captures, dst: &mut String);
+}
+
+impl<F: FnMut(&Captures) -> String> Replacer for F {
+    fn append(&mut self, caps: &Captures, dst: &mut String) {
+        dst.push_str(&self(caps))
+    }
+}
+
+/// A replacement string that refers to the capture groups of the match.
+///
+/// `$1` and `${1}` expand to the text of the group 1 (`$0` is the whole match),
+/// `${name}` to the text of the named group and `$$` to a single `$`.
+/// Groups that do not exist or did not take part in the match expand to nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Group(usize),
+    Name(String),
+}
+
+impl Template {
+    pub fn new(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                literal.push(c);
+                continue;
+            }
+
+            let part = match chars.next() {
+                Some('$') => {
+                    literal.push('$');
+                    continue;
+                }
+                Some(d) if d.is_ascii_digit() => {
+                    let mut number = d.to_string();
+                    while let Some(d) = chars.next_if(|d| d.is_ascii_digit()) {
+                        number.push(d);
+                    }
+                    parse_group_number(&number)?
+                }
+                Some('{') => {
+                    let mut reference = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => reference.push(c),
+                            None => return Err(format!("Expected }} after ${{{}", reference)),
+                        }
+                    }
+                    if !reference.is_empty() && reference.chars().all(|d| d.is_ascii_digit()) {
+                        parse_group_number(&reference)?
+                    } else if is_valid_group_name(&reference) {
+                        Part::Name(reference)
+                    } else {
+                        return Err(format!("Invalid group name in replacement: {}", reference));
+                    }
+                }
+                Some(c) => {
+                    return Err(format!(
+                        "Expected a group number, {{name}} or $ after $, got {}",
+                        c
+                    ))
+                }
+                None => return Err("Expected a group after $ got end of line".to_string()),
+            };
+
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(part);
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Appends the template expanded for the given captures to `dst`.
+    pub fn expand(&self, caps: &Captures, dst: &mut String) {
+        for part in &self.parts {
+            let group = match part {
+                Part::Literal(literal) => {
+                    dst.push_str(literal);
+                    continue;
+                }
+                Part::Group(i) => caps.get(*i),
+                Part::Name(name) => caps.name(name),
+            };
+            if let Some(m) = group {
+                dst.push_str(m.as_str());
+            }
+        }
+    }
+}
+
+fn parse_group_number(number: &str) -> Result<Part, String> {
+    number
+        .parse()
+        .map(Part::Group)
+        .map_err(|_| format!("Group number is too large: {}", number))
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, String> {
+        Self::new(template)
+    }
+}
+
+impl Replacer for Template {
+    fn append(&mut self, caps: &Captures, dst: &mut String) {
+        self.expand(caps, dst)
+    }
+}
+
+impl Replacer for &Template {
+    fn append(&mut self, caps: &Captures, dst: &mut String) {
+        self.expand(caps, dst)
+    }
+}
+
+#[cfg(test)]
+mod replace_test {
+    use super::*;
+
+    fn parts(template: &str) -> Vec<Part> {
+        match Template::new(template) {
+            Err(e) => panic!("Failed to parse {}: {}", template, e),
+            Ok(t) => t.parts,
+        }
+    }
+
+    #[test]
+    fn test_parse_template() {
+        assert_eq!(parts(""), vec![]);
+        assert_eq!(parts("abc"), vec![Part::Literal("abc".to_string())]);
+        assert_eq!(
+            parts("a$1b$$${name}$23${4}"),
+            vec![
+                Part::Literal("a".to_string()),
+                Part::Group(1),
+                Part::Literal("b$".to_string()),
+                Part::Name("name".to_string()),
+                Part::Group(23),
+                Part::Group(4),
+            ]
+        );
+        assert_eq!(
+            parts("$0é"),
+            vec![Part::Group(0), Part::Literal("é".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_template_errors() {
+        assert!(Template::new("$").is_err());
+        assert!(Template::new("a$b").is_err());
+        assert!(Template::new("${name").is_err());
+        assert!(Template::new("${}").is_err());
+        assert!(Template::new("${a b}").is_err());
+        assert!(Template::new("$99999999999999999999999").is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use std::fmt;
+use std::iter::Enumerate;
+use std::slice;
+use std::sync::Mutex;
+
+use crate::builder::DEFAULT_NFA_SIZE_LIMIT;
+use crate::dfa::DFA;
+use crate::error::RegexError;
+use crate::nfa::NFA;
+use crate::parser::{self, Flags};
+
+/// Several regular expressions matched together in a single pass over the text.
+///
+/// All the patterns are joined into one automaton in which each of them keeps
+/// its own accepting node, so the text is scanned once whatever the number of patterns.
+/// Only which patterns match is reported, use a `Regex` to find where they match.
+///
+/// ```
+/// use re::RegexSet;
+///
+/// let set = RegexSet::new(["^GET ", "error", " 404$"]).unwrap();
+/// let matches = set.matches("GET /index.html error");
+/// assert_eq!(matches.iter().collect::<Vec<_>>(), vec![0, 1]);
+/// ```
+pub struct RegexSet {
+    patterns: Vec<String>,
+    dfa: Mutex<DFA>,
+}
+
+impl RegexSet {
+    /// Compiles all the patterns, the first invalid one is reported as the error.
+    /// Each pattern is held to the default NFA size limit of a `Regex`.
+    pub fn new<I, S>(patterns: I) -> Result<Self, RegexError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns: Vec<String> = patterns
+            .into_iter()
+            .map(|p| p.as_ref().to_string())
+            .collect();
+        let nfas = patterns
+            .iter()
+            .map(|p| {
+                parser::parse_with_limit(p, Flags::default(), DEFAULT_NFA_SIZE_LIMIT)
+                    .map(|parsed| parsed.nfa)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            patterns,
+            dfa: Mutex::new(DFA::new(NFA::union(nfas))),
+        })
+    }
+
+    /// Returns true if any of the patterns matches somewhere in the text.
+    pub fn is_match(&self, text: &str) -> bool {
+        !self.patterns.is_empty() && self.lock().walk(text)
+    }
+
+    /// Returns which of the patterns match somewhere in the text.
+    pub fn matches(&self, text: &str) -> SetMatches {
+        let matched = if self.patterns.is_empty() {
+            vec![]
+        } else {
+            self.lock().walk_all(text)
+        };
+        SetMatches { matched }
+    }
+
+    /// Returns the number of patterns in the set.
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Returns the patterns in the order they were given.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DFA> {
+        match self.dfa.lock() {
+            Ok(dfa) => dfa,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl fmt::Debug for RegexSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RegexSet({:?})", self.patterns)
+    }
+}
+
+/// Which patterns of a `RegexSet` matched a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetMatches {
+    matched: Vec<bool>,
+}
+
+impl SetMatches {
+    /// Returns true if the pattern at the given index matched.
+    pub fn matched(&self, index: usize) -> bool {
+        self.matched.get(index).copied().unwrap_or(false)
+    }
+
+    /// Returns true if any pattern matched.
+    pub fn matched_any(&self) -> bool {
+        self.matched.contains(&true)
+    }
+
+    /// Returns the number of patterns in the set, matching or not.
+    pub fn len(&self) -> usize {
+        self.matched.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matched.is_empty()
+    }
+
+    /// Returns an iterator over the indices of the patterns that matched, in increasing order.
+    pub fn iter(&self) -> SetMatchesIter<'_> {
+        SetMatchesIter {
+            matched: self.matched.iter().enumerate(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a SetMatches {
+    type Item = usize;
+    type IntoIter = SetMatchesIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the indices of the matching patterns, see `SetMatches::iter`.
+#[derive(Debug)]
+pub struct SetMatchesIter<'a> {
+    matched: Enumerate<slice::Iter<'a, bool>>,
+}
+
+impl<'a> Iterator for SetMatchesIter<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.matched
+            .by_ref()
+            .find(|(_, &matched)| matched)
+            .map(|(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod set_test {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn matches(patterns: &[&str], text: &str) -> Vec<usize> {
+        RegexSet::new(patterns)
+            .unwrap()
+            .matches(text)
+            .iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_matches() {
+        let patterns = ["a+b", "b", "^c", "c$", "[xy]z", "(d|e)f"];
+        assert_eq!(matches(&patterns, "aab"), vec![0, 1]);
+        assert_eq!(matches(&patterns, "cxc"), vec![2, 3]);
+        assert_eq!(matches(&patterns, "yzef"), vec![4, 5]);
+        assert_eq!(matches(&patterns, "ccc"), vec![2, 3]);
+        assert_eq!(matches(&patterns, "xxx"), vec![]);
+        assert_eq!(matches(&patterns, "ab cyz df c"), vec![0, 1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_overlapping_patterns() {
+        let patterns = ["abc", "ab", "b", "abcd"];
+        assert_eq!(matches(&patterns, "xabcx"), vec![0, 1, 2]);
+        assert_eq!(matches(&patterns, "ab"), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_empty_pattern() {
+        assert_eq!(matches(&["", "a"], ""), vec![0]);
+        assert_eq!(matches(&["x*", "a"], "bab"), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_is_match() {
+        let set = RegexSet::new(["ab", "^c"]).unwrap();
+        assert!(set.is_match("xaby"));
+        assert!(set.is_match("cd"));
+        assert!(!set.is_match("dc"));
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.patterns(), &["ab".to_string(), "^c".to_string()]);
+    }
+
+    #[test]
+    fn test_empty_set() {
+        let set = RegexSet::new(Vec::<String>::new()).unwrap();
+        assert!(set.is_empty());
+        assert!(!set.is_match("abc"));
+        assert!(!set.matches("abc").matched_any());
+    }
+
+    #[test]
+    fn test_set_matches() {
+        let set = RegexSet::new(["a", "b", "c"]).unwrap();
+        let found = set.matches("cab");
+        assert!(found.matched_any());
+        assert!(found.matched(0) && found.matched(2));
+        assert!(!found.matched(3));
+        assert_eq!(found.len(), 3);
+        assert_eq!((&found).into_iter().count(), 3);
+        assert!(!set.matches("d").matched_any());
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = RegexSet::new(["a", "b)", "(c"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnopenedGroup);
+        assert_eq!(error.pattern(), "b)");
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let error = RegexSet::new(["a", "((a{1000}){1000}){1000}"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::SizeLimitExceeded);
+        assert_eq!(error.pattern(), "((a{1000}){1000}){1000}");
+    }
+}
@@ -198,6 +198,307 @@ macro_rules! regex_tests {
         $crate::generate_regex_test!(test160, $fn_name, "((((((((((a))))))))))", "a", Ok(true));
         $crate::generate_regex_test!(test161, $fn_name, "a[-]?c", "ac", Ok(true));
         $crate::generate_regex_test!(test162, $fn_name, "^(.+)?B", "AB", Ok(true));
+        $crate::generate_regex_test!(test163, $fn_name, "^a*b*$", "ba", Ok(false));
+        $crate::generate_regex_test!(test164, $fn_name, "^(a*|b)$", "ab", Ok(false));
+        $crate::generate_regex_test!(test165, $fn_name, "^(a+|b)*$", "abba", Ok(true));
+        $crate::generate_regex_test!(test166, $fn_name, "^$", "", Ok(true));
+        $crate::generate_regex_test!(test167, $fn_name, "^(|a)$", "aa", Ok(false));
+        $crate::generate_regex_test!(test168, $fn_name, "^a{3}$", "aaa", Ok(true));
+        $crate::generate_regex_test!(test169, $fn_name, "^a{3}$", "aa", Ok(false));
+        $crate::generate_regex_test!(test170, $fn_name, "^a{3}$", "aaaa", Ok(false));
+        $crate::generate_regex_test!(test171, $fn_name, "^a{2,}$", "a", Ok(false));
+        $crate::generate_regex_test!(test172, $fn_name, "^a{2,}$", "aaaaa", Ok(true));
+        $crate::generate_regex_test!(test173, $fn_name, "^a{0,}$", "", Ok(true));
+        $crate::generate_regex_test!(test174, $fn_name, "^a{1,3}b$", "b", Ok(false));
+        $crate::generate_regex_test!(test175, $fn_name, "^a{1,3}b$", "aaab", Ok(true));
+        $crate::generate_regex_test!(test176, $fn_name, "^a{1,3}b$", "aaaab", Ok(false));
+        $crate::generate_regex_test!(test177, $fn_name, "^a{0,2}$", "", Ok(true));
+        $crate::generate_regex_test!(test178, $fn_name, "^(ab|c){2}$", "abc", Ok(true));
+        $crate::generate_regex_test!(test179, $fn_name, "^(ab|c){2}$", "abcab", Ok(false));
+        $crate::generate_regex_test!(test180, $fn_name, "^x[ab]{2,3}y$", "xbaby", Ok(true));
+        $crate::generate_regex_test!(test181, $fn_name, "^xa{0}y$", "xy", Ok(true));
+        $crate::generate_regex_test!(test182, $fn_name, "^xa{0}y$", "xay", Ok(false));
+        $crate::generate_regex_test!(test183, $fn_name, "^(a*){2}$", "aaa", Ok(true));
+        $crate::generate_regex_test!(test184, $fn_name, "a{2}{3}", "aaaaaa", Err(""));
+        $crate::generate_regex_test!(test185, $fn_name, "a{3,1}", "aaa", Err(""));
+        $crate::generate_regex_test!(test186, $fn_name, "a{2", "a{2", Err(""));
+        $crate::generate_regex_test!(test187, $fn_name, "^[a-z0-9_]+$", "ab_09z", Ok(true));
+        $crate::generate_regex_test!(test188, $fn_name, "^[a-z0-9_]+$", "ab-09z", Ok(false));
+        $crate::generate_regex_test!(test189, $fn_name, "[^a-z]", "abz", Ok(false));
+        $crate::generate_regex_test!(test190, $fn_name, "[^a-z]", "abZ", Ok(true));
+        $crate::generate_regex_test!(test191, $fn_name, "^[a-]+$", "a-a", Ok(true));
+        $crate::generate_regex_test!(test192, $fn_name, "^[-a]+$", "-b", Ok(false));
+        $crate::generate_regex_test!(test193, $fn_name, "[z-a]", "a", Err(""));
+        $crate::generate_regex_test!(test194, $fn_name, "^[\\[-\\]]$", "\\", Ok(true));
+        $crate::generate_regex_test!(test195, $fn_name, "^[à-ë]{2}$", "éè", Ok(true));
+        $crate::generate_regex_test!(test196, $fn_name, "^\\d+$", "0123456789", Ok(true));
+        $crate::generate_regex_test!(test197, $fn_name, "^\\d+$", "12a", Ok(false));
+        $crate::generate_regex_test!(test198, $fn_name, "^\\D+$", "abc", Ok(true));
+        $crate::generate_regex_test!(test199, $fn_name, "\\D", "123", Ok(false));
+        $crate::generate_regex_test!(test200, $fn_name, "^\\w+$", "foo_Bar9", Ok(true));
+        $crate::generate_regex_test!(test201, $fn_name, "\\W", "foo_Bar9", Ok(false));
+        $crate::generate_regex_test!(test202, $fn_name, "^\\W+$", "-+ !", Ok(true));
+        $crate::generate_regex_test!(test203, $fn_name, "^a\\sb$", "a\tb", Ok(true));
+        $crate::generate_regex_test!(test204, $fn_name, "^a\\Sb$", "a b", Ok(false));
+        $crate::generate_regex_test!(test205, $fn_name, "^[\\d.-]+$", "1.5-2", Ok(true));
+        $crate::generate_regex_test!(test206, $fn_name, "^[\\d.-]+$", "1,5", Ok(false));
+        $crate::generate_regex_test!(test207, $fn_name, "^[^\\s]+$", "ab", Ok(true));
+        $crate::generate_regex_test!(test208, $fn_name, "^[\\s\\d]+$", "1 2", Ok(true));
+        $crate::generate_regex_test!(test209, $fn_name, "^[\\Wa]+$", "a-a", Ok(true));
+        $crate::generate_regex_test!(test210, $fn_name, "^[\\Wa]+$", "a-b", Ok(false));
+        $crate::generate_regex_test!(test211, $fn_name, "^\\w+$", "日本語", Ok(true));
+        $crate::generate_regex_test!(test212, $fn_name, "^\\d$", "٣", Ok(true));
+        $crate::generate_regex_test!(test213, $fn_name, "^\\s$", "\u{2003}", Ok(true));
+        $crate::generate_regex_test!(test214, $fn_name, "^\\p{L}+$", "héllo", Ok(true));
+        $crate::generate_regex_test!(test215, $fn_name, "^\\pL+$", "hé11o", Ok(false));
+        $crate::generate_regex_test!(test216, $fn_name, "^\\p{Lu}\\p{Ll}+$", "Été", Ok(true));
+        $crate::generate_regex_test!(test217, $fn_name, "^\\p{Lu}\\p{Ll}+$", "été", Ok(false));
+        $crate::generate_regex_test!(test218, $fn_name, "\\p{Greek}", "abc λ", Ok(true));
+        $crate::generate_regex_test!(test219, $fn_name, "^\\P{Greek}+$", "abc λ", Ok(false));
+        $crate::generate_regex_test!(test220, $fn_name, "^\\p{Han}{2}$", "漢字", Ok(true));
+        $crate::generate_regex_test!(test221, $fn_name, "^[\\p{Greek}\\d]+$", "λ1μ", Ok(true));
+        $crate::generate_regex_test!(test222, $fn_name, "^[^\\p{Greek}]+$", "abc", Ok(true));
+        $crate::generate_regex_test!(test223, $fn_name, "^[\\P{Nd}]+$", "a1", Ok(false));
+        $crate::generate_regex_test!(test224, $fn_name, "^\\p{White_Space}$", "\u{A0}", Ok(true));
+        $crate::generate_regex_test!(test225, $fn_name, "\\p{Unknown_Class}", "a", Err(""));
+        $crate::generate_regex_test!(test226, $fn_name, "^[[:alpha:]]+$", "abcXYZ", Ok(true));
+        $crate::generate_regex_test!(test227, $fn_name, "^[[:alpha:]]+$", "abc1", Ok(false));
+        $crate::generate_regex_test!(test228, $fn_name, "^[[:alnum:]_]+$", "a_1", Ok(true));
+        $crate::generate_regex_test!(test229, $fn_name, "[[:space:]]", "a\tb", Ok(true));
+        $crate::generate_regex_test!(test230, $fn_name, "^[^[:punct:]]+$", "a b", Ok(true));
+        $crate::generate_regex_test!(test231, $fn_name, "^[^[:punct:]]+$", "a,b", Ok(false));
+        $crate::generate_regex_test!(test232, $fn_name, "^[[:^digit:]]+$", "ab", Ok(true));
+        $crate::generate_regex_test!(test233, $fn_name, "^[[:^digit:]]+$", "a1", Ok(false));
+        $crate::generate_regex_test!(
+            test234,
+            $fn_name,
+            "^[[:upper:][:digit:]]+$",
+            "A1B",
+            Ok(true)
+        );
+        $crate::generate_regex_test!(test235, $fn_name, "^[[:xdigit:]]+$", "c0ffee", Ok(true));
+        $crate::generate_regex_test!(test236, $fn_name, "[[:foo:]]", "a", Err(""));
+        $crate::generate_regex_test!(test237, $fn_name, "^a\\|b$", "a|b", Ok(true));
+        $crate::generate_regex_test!(test238, $fn_name, "^a\\|b$", "a", Ok(false));
+        $crate::generate_regex_test!(test239, $fn_name, "^\\^\\$$", "^$", Ok(true));
+        $crate::generate_regex_test!(test240, $fn_name, "^\\{1\\}$", "{1}", Ok(true));
+        $crate::generate_regex_test!(test241, $fn_name, "^[\\-\\^\\]]+$", "-^]", Ok(true));
+        $crate::generate_regex_test!(test242, $fn_name, "^a\\nb\\tc$", "a\nb\tc", Ok(true));
+        $crate::generate_regex_test!(test243, $fn_name, "^\\r\\0$", "\r\0", Ok(true));
+        $crate::generate_regex_test!(test244, $fn_name, "^\\x41\\x{42}\\u{E9}$", "ABé", Ok(true));
+        $crate::generate_regex_test!(test245, $fn_name, "^[\\x41-\\x43]+$", "ABCA", Ok(true));
+        $crate::generate_regex_test!(test246, $fn_name, "^[\\x41-\\x43]+$", "ABD", Ok(false));
+        $crate::generate_regex_test!(test247, $fn_name, "^[\\n\\t]+$", "\n\t", Ok(true));
+        $crate::generate_regex_test!(test248, $fn_name, "^\\u{1F600}+$", "😀😀", Ok(true));
+        $crate::generate_regex_test!(test249, $fn_name, "\\x{110000}", "a", Err(""));
+        $crate::generate_regex_test!(test250, $fn_name, "\\xG0", "a", Err(""));
+        $crate::generate_regex_test!(test251, $fn_name, "^(?i)abc$", "aBC", Ok(true));
+        $crate::generate_regex_test!(test252, $fn_name, "^a(?i)bc$", "AbC", Ok(false));
+        $crate::generate_regex_test!(test253, $fn_name, "^a(?i)bc$", "aBC", Ok(true));
+        $crate::generate_regex_test!(test254, $fn_name, "^(?i:a)b$", "Ab", Ok(true));
+        $crate::generate_regex_test!(test255, $fn_name, "^(?i:a)b$", "AB", Ok(false));
+        $crate::generate_regex_test!(test256, $fn_name, "^(?i)a(?-i)b$", "Ab", Ok(true));
+        $crate::generate_regex_test!(test257, $fn_name, "^(?i)a(?-i)b$", "AB", Ok(false));
+        $crate::generate_regex_test!(test258, $fn_name, "^((?i)a)b$", "AB", Ok(false));
+        $crate::generate_regex_test!(test259, $fn_name, "^(?i)a|b$", "B", Ok(true));
+        $crate::generate_regex_test!(test260, $fn_name, "^a.b$", "a\nb", Ok(false));
+        $crate::generate_regex_test!(test261, $fn_name, "^(?s)a.b$", "a\nb", Ok(true));
+        $crate::generate_regex_test!(test262, $fn_name, "(?m)^b$", "a\nb\nc", Ok(true));
+        $crate::generate_regex_test!(test263, $fn_name, "^b$", "a\nb\nc", Ok(false));
+        $crate::generate_regex_test!(test264, $fn_name, "(?m:^b$)", "a\nb\nc", Ok(true));
+        $crate::generate_regex_test!(test265, $fn_name, "^(?i)[a-c]+$", "AbC", Ok(true));
+        $crate::generate_regex_test!(test266, $fn_name, "(?q)a", "a", Err(""));
+        $crate::generate_regex_test!(test267, $fn_name, "^(?i)k$", "K", Ok(true));
+        $crate::generate_regex_test!(test268, $fn_name, "^(?i)k$", "\u{212A}", Ok(true));
+        $crate::generate_regex_test!(test269, $fn_name, "^(?i)\u{212A}$", "k", Ok(true));
+        $crate::generate_regex_test!(test270, $fn_name, "^(?i)K$", "\u{212A}", Ok(true));
+        $crate::generate_regex_test!(test271, $fn_name, "^(?i)[a-k]$", "\u{212A}", Ok(true));
+        $crate::generate_regex_test!(test272, $fn_name, "^(?i)[^k]$", "\u{212A}", Ok(false));
+        $crate::generate_regex_test!(test273, $fn_name, "^(?i)[^k]$", "x", Ok(true));
+        $crate::generate_regex_test!(test274, $fn_name, "^(?i)σ$", "ς", Ok(true));
+        $crate::generate_regex_test!(test275, $fn_name, "^(?i)Σ$", "ς", Ok(true));
+        $crate::generate_regex_test!(test276, $fn_name, "^(?i)ǆ$", "ǅ", Ok(true));
+        $crate::generate_regex_test!(test277, $fn_name, "^(?i)é$", "É", Ok(true));
+        $crate::generate_regex_test!(test278, $fn_name, "^(?i)ß$", "ẞ", Ok(true));
+        $crate::generate_regex_test!(test279, $fn_name, "^(?i)ß$", "SS", Ok(false));
+        $crate::generate_regex_test!(test280, $fn_name, "^(?i)\\p{Lu}$", "a", Ok(true));
+        $crate::generate_regex_test!(test281, $fn_name, "^(?i)жар$", "ЖАР", Ok(true));
+        $crate::generate_regex_test!(test282, $fn_name, "^k$", "\u{212A}", Ok(false));
+        $crate::generate_regex_test!(test283, $fn_name, "^a*?$", "aaa", Ok(true));
+        $crate::generate_regex_test!(test284, $fn_name, "^a+?b$", "aab", Ok(true));
+        $crate::generate_regex_test!(test285, $fn_name, "^a??b$", "ab", Ok(true));
+        $crate::generate_regex_test!(test286, $fn_name, "^a{1,2}?$", "aa", Ok(true));
+        $crate::generate_regex_test!(test287, $fn_name, "^a{2,}?$", "a", Ok(false));
+        $crate::generate_regex_test!(test288, $fn_name, "a*?b", "xxb", Ok(true));
+        $crate::generate_regex_test!(test289, $fn_name, "^(a|b)+?$", "abba", Ok(true));
+        $crate::generate_regex_test!(test290, $fn_name, "a???", "a", Err(""));
+        $crate::generate_regex_test!(test291, $fn_name, "a*??", "a", Err(""));
+        $crate::generate_regex_test!(test292, $fn_name, "a{2}??", "a", Err(""));
+        $crate::generate_regex_test!(test293, $fn_name, "?a", "a", Err(""));
+        $crate::generate_regex_test!(test294, $fn_name, "\\bcat\\b", "a cat sat", Ok(true));
+        $crate::generate_regex_test!(test295, $fn_name, "\\bcat\\b", "concatenate", Ok(false));
+        $crate::generate_regex_test!(test296, $fn_name, "\\bcat\\b", "cat", Ok(true));
+        $crate::generate_regex_test!(test297, $fn_name, "\\bcat\\b", "cats", Ok(false));
+        $crate::generate_regex_test!(test298, $fn_name, "\\Bcat\\B", "concatenate", Ok(true));
+        $crate::generate_regex_test!(test299, $fn_name, "\\Bcat", "cat", Ok(false));
+        $crate::generate_regex_test!(test300, $fn_name, "\\b", "", Ok(false));
+        $crate::generate_regex_test!(test301, $fn_name, "\\B", "", Ok(true));
+        $crate::generate_regex_test!(test302, $fn_name, "\\b", " ", Ok(false));
+        $crate::generate_regex_test!(test303, $fn_name, "\\b", "a", Ok(true));
+        $crate::generate_regex_test!(test304, $fn_name, "^\\b$", "", Ok(false));
+        $crate::generate_regex_test!(test305, $fn_name, "\\bé\\b", "café", Ok(false));
+        $crate::generate_regex_test!(test306, $fn_name, "\\bcafé\\b", "un café.", Ok(true));
+        $crate::generate_regex_test!(test307, $fn_name, "é\\b", "é", Ok(true));
+        $crate::generate_regex_test!(test308, $fn_name, "a\\b\\b\\bb", "ab", Ok(false));
+        $crate::generate_regex_test!(test309, $fn_name, "a\\B\\Bb", "ab", Ok(true));
+        $crate::generate_regex_test!(test310, $fn_name, "\\b_\\b", "a _ b", Ok(true));
+        $crate::generate_regex_test!(test311, $fn_name, "\\bx\\b", "a\nx\nb", Ok(true));
+        $crate::generate_regex_test!(test312, $fn_name, "(\\bfoo|bar\\b)", "xbar", Ok(true));
+        $crate::generate_regex_test!(test313, $fn_name, "\\b\\w+\\b", "!!", Ok(false));
+        $crate::generate_regex_test!(test314, $fn_name, "\\Aab", "ab", Ok(true));
+        $crate::generate_regex_test!(test315, $fn_name, "\\Aab", "cab", Ok(false));
+        $crate::generate_regex_test!(test316, $fn_name, "ab\\z", "cab", Ok(true));
+        $crate::generate_regex_test!(test317, $fn_name, "ab\\z", "abc", Ok(false));
+        $crate::generate_regex_test!(test318, $fn_name, "(?m)\\Ab", "a\nb", Ok(false));
+        $crate::generate_regex_test!(test319, $fn_name, "(?m)a\\z", "a\nb", Ok(false));
+        $crate::generate_regex_test!(test320, $fn_name, "(?m)^b\\b", "a\nb c", Ok(true));
+        $crate::generate_regex_test!(test321, $fn_name, "\\b^a", "a", Ok(true));
+        $crate::generate_regex_test!(test322, $fn_name, "a$\\b", "a", Ok(true));
+        $crate::generate_regex_test!(test323, $fn_name, "[\\b]", "b", Err(""));
+        $crate::generate_regex_test!(test324, $fn_name, "\\b+", "a", Ok(true));
+        $crate::generate_regex_test!(test325, $fn_name, "(?m)^b$", "a\r\nb\r\nc", Ok(false));
+        $crate::generate_regex_test!(test326, $fn_name, "(?mR)^b$", "a\r\nb\r\nc", Ok(true));
+        $crate::generate_regex_test!(test327, $fn_name, "(?mR)^b$", "a\rb\rc", Ok(true));
+        $crate::generate_regex_test!(test328, $fn_name, "(?mR)^b$", "a\nb\nc", Ok(true));
+        $crate::generate_regex_test!(test329, $fn_name, "(?mR)^$", "a\r\nb", Ok(false));
+        $crate::generate_regex_test!(test330, $fn_name, "(?mR)^$", "a\r\n\r\nb", Ok(true));
+        $crate::generate_regex_test!(test331, $fn_name, "(?mR)^$", "a\n\nb", Ok(true));
+        $crate::generate_regex_test!(test332, $fn_name, "(?mR)a$", "a\r\n", Ok(true));
+        $crate::generate_regex_test!(test333, $fn_name, "(?mR)^\n", "\r\n", Ok(false));
+        $crate::generate_regex_test!(test334, $fn_name, "(?R)^a.b$", "a\rb", Ok(false));
+        $crate::generate_regex_test!(test335, $fn_name, "^a.b$", "a\rb", Ok(true));
+        $crate::generate_regex_test!(test336, $fn_name, "(?Rs)^a.b$", "a\rb", Ok(true));
+        $crate::generate_regex_test!(test337, $fn_name, "(?R)^b$", "a\r\nb", Ok(false));
+        $crate::generate_regex_test!(test338, $fn_name, "(?mR)\r$", "a\r", Ok(true));
+        $crate::generate_regex_test!(test339, $fn_name, "(?mR)^$", "", Ok(true));
+        $crate::generate_regex_test!(test340, $fn_name, "^[a-c[x-z]]+$", "axcz", Ok(true));
+        $crate::generate_regex_test!(test341, $fn_name, "^[a-c[x-z]]+$", "ad", Ok(false));
+        $crate::generate_regex_test!(
+            test342,
+            $fn_name,
+            "^[\\p{L}&&\\p{Greek}]+$",
+            "λόγος",
+            Ok(true)
+        );
+        $crate::generate_regex_test!(
+            test343,
+            $fn_name,
+            "^[\\p{L}&&\\p{Greek}]+$",
+            "λx",
+            Ok(false)
+        );
+        $crate::generate_regex_test!(test344, $fn_name, "^[a-z--[aeiou]]+$", "rhythm", Ok(true));
+        $crate::generate_regex_test!(test345, $fn_name, "^[a-z--[aeiou]]+$", "vowel", Ok(false));
+        $crate::generate_regex_test!(test346, $fn_name, "^[\\w--\\d]+$", "abc_", Ok(true));
+        $crate::generate_regex_test!(test347, $fn_name, "^[\\w--\\d]+$", "a1", Ok(false));
+        $crate::generate_regex_test!(test348, $fn_name, "^[a-f~~d-k]+$", "abcghk", Ok(true));
+        $crate::generate_regex_test!(test349, $fn_name, "^[a-f~~d-k]+$", "e", Ok(false));
+        $crate::generate_regex_test!(test350, $fn_name, "^[^a-z&&[^aeiou]]$", "e", Ok(true));
+        $crate::generate_regex_test!(test351, $fn_name, "^[^a-z&&[^aeiou]]$", "b", Ok(false));
+        $crate::generate_regex_test!(test352, $fn_name, "^[[:alpha:]--[a-z]]+$", "ABC", Ok(true));
+        $crate::generate_regex_test!(test353, $fn_name, "^[[:alpha:]--[a-z]]+$", "AbC", Ok(false));
+        $crate::generate_regex_test!(test354, $fn_name, "^[a&b]+$", "a&b", Ok(true));
+        $crate::generate_regex_test!(test355, $fn_name, "^[a-z&&]$", "a", Err(""));
+        $crate::generate_regex_test!(test356, $fn_name, "[a[b]", "a", Err(""));
+        $crate::generate_regex_test!(test357, $fn_name, "^[\\[]$", "[", Ok(true));
+        $crate::generate_regex_test!(test358, $fn_name, "(?i)^[a-z--k]$", "K", Ok(false));
+        $crate::generate_regex_test!(test359, $fn_name, "(?x)^a b c$", "abc", Ok(true));
+        $crate::generate_regex_test!(test360, $fn_name, "(?x)^a b c$", "a b c", Ok(false));
+        $crate::generate_regex_test!(test361, $fn_name, "(?x)^a\\ b$", "a b", Ok(true));
+        $crate::generate_regex_test!(test362, $fn_name, "(?x)^a[ ]b$", "a b", Ok(true));
+        $crate::generate_regex_test!(test363, $fn_name, "(?x)^a # comment\n b$", "ab", Ok(true));
+        $crate::generate_regex_test!(test364, $fn_name, "(?x)b#a", "a", Ok(false));
+        $crate::generate_regex_test!(test365, $fn_name, "(?x)^a\\#b$", "a#b", Ok(true));
+        $crate::generate_regex_test!(test366, $fn_name, "(?x)^a\tb$", "ab", Ok(true));
+        $crate::generate_regex_test!(test367, $fn_name, "(?x)^ab #$", "ab", Ok(true));
+        $crate::generate_regex_test!(test368, $fn_name, "^a b$", "a b", Ok(true));
+        $crate::generate_regex_test!(test369, $fn_name, "(?x)^a{ 2}$", "aa", Err(""));
+        $crate::generate_regex_test!(test370, $fn_name, "^(é|ü)+$", "éüé", Ok(true));
+        $crate::generate_regex_test!(test371, $fn_name, "^é|ü$", "ü", Ok(true));
+        $crate::generate_regex_test!(test372, $fn_name, "^à(?i:É)+$", "àéÉ", Ok(true));
+        $crate::generate_regex_test!(test373, $fn_name, "^ü{2}(?:ö|[é-ê])$", "üüê", Ok(true));
+        $crate::generate_regex_test!(test374, $fn_name, "^ü{2}(?:ö|[é-ê])$", "üüë", Ok(false));
+        $crate::generate_regex_test!(test375, $fn_name, "(?x)^ é # ü\n |ö$", "ö", Ok(true));
+        $crate::generate_regex_test!(test376, $fn_name, "é(ü", "éü", Err(""));
+        $crate::generate_regex_test!(test377, $fn_name, "^(?:a|b+)+b$", "b", Ok(false));
+        $crate::generate_regex_test!(test378, $fn_name, "^(?:a|b+)+b$", "abbb", Ok(true));
+        $crate::generate_regex_test!(test379, $fn_name, "^(?:x|é+)+$", "", Ok(false));
+        $crate::generate_regex_test!(test380, $fn_name, "^(?:x|é+)*$", "éxé", Ok(true));
+        $crate::generate_regex_test!(test381, $fn_name, "^(?:a|b+)*c$", "b", Ok(false));
+        $crate::generate_regex_test!(test382, $fn_name, "^(?:a|b+){2,}c$", "bc", Ok(false));
+        $crate::generate_regex_test!(test383, $fn_name, "^(?:a|b+){2,}c$", "abbc", Ok(true));
+        $crate::generate_regex_test!(test384, $fn_name, "^(?:a|b+)+?b$", "b", Ok(false));
+        $crate::generate_regex_test!(test385, $fn_name, "^[^a&&b]$", "a", Ok(true));
+        $crate::generate_regex_test!(test386, $fn_name, "^[^a&&b]+$", "é\n", Ok(true));
+        $crate::generate_regex_test!(test387, $fn_name, "[^\\s\\S]", "a", Err(""));
+        $crate::generate_regex_test!(test388, $fn_name, "[^\\d\\D]|a", "a", Err(""));
     };
 }
 
+#[macro_export]
+macro_rules! generate_split_test {
+    ($($name:ident, $fn_name:ident, $pattern:expr, $string:expr, $limit:expr, $expected:expr)*) => {
+    $(
+        #[test]
+        fn $name() {
+            let limit: Option<usize> = $limit;
+            let res = $fn_name($pattern, $string, limit);
+            let expected: &[&str] = $expected;
+            assert_eq!(
+                res, expected,
+                "Testing that /{}/ splits \"{}\" with limit {:?} into {:?}",
+                $pattern, $string, limit, expected
+            );
+        }
+    )*
+    }
+}
+
+#[macro_export]
+macro_rules! split_tests {
+    ($fn_name:ident) => {
+        $crate::generate_split_test!(split0, $fn_name, ",", "a,b,c", None, &["a", "b", "c"]);
+        $crate::generate_split_test!(split1, $fn_name, ",", "abc", None, &["abc"]);
+        $crate::generate_split_test!(split2, $fn_name, ",", "", None, &[""]);
+        $crate::generate_split_test!(split3, $fn_name, ",", ",a,", None, &["", "a", ""]);
+        $crate::generate_split_test!(split4, $fn_name, ",", "a,,b", None, &["a", "", "b"]);
+        $crate::generate_split_test!(
+            split5,
+            $fn_name,
+            " *, *",
+            "a , b,c ,d",
+            None,
+            &["a", "b", "c", "d"]
+        );
+        $crate::generate_split_test!(
+            split6,
+            $fn_name,
+            "[ ,]+",
+            " a, b ",
+            None,
+            &["", "a", "b", ""]
+        );
+        $crate::generate_split_test!(split7, $fn_name, "", "abc", None, &["", "a", "b", "c", ""]);
+        $crate::generate_split_test!(split8, $fn_name, "x*", "axxb", None, &["", "a", "b", ""]);
+        $crate::generate_split_test!(split9, $fn_name, "", "", None, &["", ""]);
+        $crate::generate_split_test!(split10, $fn_name, "é", "aébéc", None, &["a", "b", "c"]);
+        $crate::generate_split_test!(split11, $fn_name, ",", "a,b,c", Some(0), &[]);
+        $crate::generate_split_test!(split12, $fn_name, ",", "a,b,c", Some(1), &["a,b,c"]);
+        $crate::generate_split_test!(split13, $fn_name, ",", "a,b,c", Some(2), &["a", "b,c"]);
+        $crate::generate_split_test!(split14, $fn_name, ",", "a,b,c", Some(3), &["a", "b", "c"]);
+        $crate::generate_split_test!(split15, $fn_name, ",", "a,b,c", Some(9), &["a", "b", "c"]);
+        $crate::generate_split_test!(split16, $fn_name, ",", ",a,", Some(2), &["", "a,"]);
+        $crate::generate_split_test!(split17, $fn_name, ",", "", Some(2), &[""]);
+        $crate::generate_split_test!(split18, $fn_name, "", "abc", Some(3), &["", "a", "bc"]);
+    };
+}